@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::ConfigCliError;
+use crate::utils::{copy_path, get_state_dir, remove_path, timestamp, unix_time, ConfigResult};
+use crate::{try_create_file, try_read_and_parse, try_read_dir, try_write_file};
+
+pub mod list;
+pub mod restore;
+pub mod set_retention;
+
+pub use list::*;
+pub use restore::*;
+pub use set_retention::*;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupEntry {
+    /// Where the file was before it got overwritten or deleted
+    pub original: PathBuf,
+    /// The copy, relative to the `files` directory of the backup
+    pub stored: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupManifest {
+    pub created: u64,
+    /// The command that replaced the files
+    pub operation: String,
+    pub entries: Vec<BackupEntry>,
+}
+
+/// How many backups are kept, older ones get pruned whenever a new backup is taken
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Retention {
+    pub keep: usize,
+    /// Backups older than this are pruned even if there are fewer than `keep`
+    pub max_age_days: Option<u64>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            keep: 20,
+            max_age_days: Some(30),
+        }
+    }
+}
+
+/// Copies of everything an operation is about to overwrite or delete
+///
+/// Nothing is written until the first snapshot, so operations that don't replace anything leave no backup behind.
+pub struct Backup {
+    dir: PathBuf,
+    manifest: BackupManifest,
+}
+
+pub fn backups_dir() -> ConfigResult<PathBuf> {
+    Ok(get_state_dir()?.join(".backups"))
+}
+
+impl Backup {
+    pub fn begin(operation: &str) -> ConfigResult<Backup> {
+        let created = unix_time();
+        let base = backups_dir()?;
+
+        // Two operations in the same second get distinct ids that still sort in order
+        let mut id = timestamp(created);
+        let mut count = 1;
+        while base.join(&id).exists() {
+            id = format!("{}-{}", timestamp(created), count);
+            count += 1;
+        }
+
+        Ok(Backup {
+            dir: base.join(id),
+            manifest: BackupManifest {
+                created,
+                operation: operation.to_owned(),
+                entries: vec![],
+            },
+        })
+    }
+
+    /// Copies whatever is at the path, symlinks are kept as links
+    pub fn snapshot(&mut self, path: &Path) -> ConfigResult<()> {
+        if std::fs::symlink_metadata(path).is_err()
+            || self.manifest.entries.iter().any(|x| x.original == path)
+        {
+            return Ok(());
+        }
+
+        let stored = self.manifest.entries.len().to_string();
+        if let Err(err) = copy_path(path, self.dir.join("files").join(&stored)) {
+            return Err(ConfigCliError::CopyError(err).at(path));
+        }
+        self.manifest.entries.push(BackupEntry {
+            original: path.to_path_buf(),
+            stored,
+        });
+        self.write_manifest()
+    }
+
+    // The manifest is rewritten after every snapshot, so an interrupted operation still leaves a usable backup
+    fn write_manifest(&self) -> ConfigResult<()> {
+        let manifest_path = self.dir.join("manifest.toml");
+        if !manifest_path.exists() {
+            try_create_file!(manifest_path.clone());
+        }
+        try_write_file!(manifest_path, &self.manifest);
+        Ok(())
+    }
+
+    /// Prunes old backups, returning the id of this one if anything was backed up
+    pub fn finish(self) -> ConfigResult<Option<String>> {
+        if self.manifest.entries.is_empty() {
+            return Ok(None);
+        }
+        let id = backup_id(&self.dir);
+        prune_backups(&read_retention()?, &id)?;
+        Ok(Some(id))
+    }
+}
+
+fn backup_id(dir: &Path) -> String {
+    dir.file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+pub fn read_manifest(id: &str) -> ConfigResult<BackupManifest> {
+    let path = backups_dir()?.join(id).join("manifest.toml");
+    if !path.exists() {
+        return Err(ConfigCliError::InvalidBackup(id.to_owned()));
+    }
+    Ok(try_read_and_parse!(path, BackupManifest))
+}
+
+/// Ids of every backup, oldest first
+pub fn backup_ids() -> ConfigResult<Vec<String>> {
+    let base = backups_dir()?;
+    if !base.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut ids = vec![];
+    for entry in try_read_dir!(&base)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(&base)),
+        };
+        if entry.path().join("manifest.toml").exists() {
+            ids.push(backup_id(&entry.path()));
+        }
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+pub fn read_retention() -> ConfigResult<Retention> {
+    let path = backups_dir()?.join("retention.toml");
+    if !path.exists() {
+        return Ok(Retention::default());
+    }
+    Ok(try_read_and_parse!(path, Retention))
+}
+
+/// Removes the backups beyond the retention policy, never the one given
+pub fn prune_backups(retention: &Retention, keep_id: &str) -> ConfigResult<()> {
+    let ids = backup_ids()?;
+    let now = unix_time();
+    let excess = ids.len().saturating_sub(retention.keep);
+
+    for (index, id) in ids.iter().enumerate() {
+        if id == keep_id {
+            continue;
+        }
+        let expired = match retention.max_age_days {
+            Some(days) => now.saturating_sub(read_manifest(id)?.created) > days * 86400,
+            None => false,
+        };
+        if index < excess || expired {
+            let path = backups_dir()?.join(id);
+            if let Err(err) = remove_path(&path) {
+                return Err(ConfigCliError::DeleteError(err).at(&path));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::{backup_ids, read_manifest};
+use crate::utils::{timestamp, ConfigResult};
+
+#[derive(Debug, Serialize)]
+pub struct BackupInfo {
+    pub id: String,
+    pub created: String,
+    pub operation: String,
+    pub files: Vec<PathBuf>,
+}
+
+/// Lists the backups, newest first
+pub fn list_backups() -> ConfigResult<Vec<BackupInfo>> {
+    let mut backups = vec![];
+    for id in backup_ids()?.into_iter().rev() {
+        let manifest = read_manifest(&id)?;
+        backups.push(BackupInfo {
+            id,
+            created: timestamp(manifest.created),
+            operation: manifest.operation,
+            files: manifest.entries.into_iter().map(|x| x.original).collect(),
+        });
+    }
+    Ok(backups)
+}
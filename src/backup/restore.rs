@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use super::{backups_dir, read_manifest, Backup, BackupEntry};
+use crate::error::ConfigCliError;
+use crate::utils::{absolute_path, copy_path, ConfigResult};
+
+/// Puts the files of the backup back, or only the one at the path
+///
+/// Whatever is at those locations now gets backed up itself first, so a restore can be undone.
+pub fn restore_backup(id: String, path: Option<PathBuf>) -> ConfigResult<Option<String>> {
+    let manifest = read_manifest(&id)?;
+
+    let path = path.map(absolute_path).transpose()?;
+    let entries: Vec<&BackupEntry> = manifest
+        .entries
+        .iter()
+        .filter(|x| path.is_none() || path.as_ref() == Some(&x.original))
+        .collect();
+    if let Some(path) = &path {
+        if entries.is_empty() {
+            return Err(ConfigCliError::InvalidBackup(format!(
+                "{} has no copy of {}",
+                id,
+                path.display()
+            )));
+        }
+    }
+
+    let mut backup = Backup::begin(&format!("backup restore {}", id))?;
+    for entry in entries {
+        backup.snapshot(&entry.original)?;
+
+        if let Ok(meta) = std::fs::symlink_metadata(&entry.original) {
+            let removed = match meta.is_dir() {
+                true => std::fs::remove_dir_all(&entry.original),
+                false => std::fs::remove_file(&entry.original),
+            };
+            if let Err(err) = removed {
+                return Err(ConfigCliError::DeleteError(err).at(&entry.original));
+            }
+        }
+
+        let stored = backups_dir()?.join(&id).join("files").join(&entry.stored);
+        if let Err(err) = copy_path(stored, &entry.original) {
+            return Err(ConfigCliError::CopyError(err).at(&entry.original));
+        }
+    }
+    backup.finish()
+}
@@ -0,0 +1,33 @@
+use std::path::Path;
+
+use super::{backups_dir, prune_backups, read_retention};
+use crate::error::ConfigCliError;
+use crate::utils::ConfigResult;
+use crate::{try_create_file, try_write_file};
+
+/// Changes how many backups are kept and prunes the ones beyond it right away
+pub fn set_retention(
+    keep: Option<usize>,
+    max_age_days: Option<u64>,
+    no_max_age: bool,
+) -> ConfigResult<()> {
+    let mut retention = read_retention()?;
+    if let Some(keep) = keep {
+        retention.keep = keep;
+    }
+    if max_age_days.is_some() || no_max_age {
+        retention.max_age_days = max_age_days;
+    }
+
+    let dir = backups_dir()?;
+    if let Err(err) = std::fs::create_dir_all(&dir) {
+        return Err(ConfigCliError::FileCreationError(err).at(&dir));
+    }
+    let path = dir.join("retention.toml");
+    if !Path::new(&path).exists() {
+        try_create_file!(path.clone());
+    }
+    try_write_file!(path, &retention);
+
+    prune_backups(&retention, "")
+}
@@ -1,22 +1,54 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
+use crate::diff::DiffSide;
+
+pub mod backup_actions;
 pub mod config_actions;
 pub mod dependency_actions;
 pub mod device_actions;
 pub mod git_actions;
+pub mod secret_actions;
 pub mod theme_actions;
+pub mod variable_actions;
 
+pub use backup_actions::BackupActions;
 pub use config_actions::ConfigActions;
 pub use dependency_actions::DependencyActions;
 pub use device_actions::DeviceActions;
 pub use git_actions::GitActions;
+pub use secret_actions::SecretActions;
 pub use theme_actions::ThemeActions;
+pub use variable_actions::VariableActions;
+
+const EXIT_CODES: &str = "Exit codes:
+  0  success
+  2  invalid input, like an unknown theme or config, or a failing hook
+  3  conflict, like an unmanaged file at a target or a leaked secret
+  4  reading, writing or parsing files failed
+  5  git failed
+  6  the package manager failed";
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = EXIT_CODES)]
 pub struct ConfigCli {
     #[command(subcommand)]
     pub command: ConfigSubCommands,
+
+    #[arg(short, long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Directory holding the themes, overrides $CONFIG_MANAGER_HOME and $XDG_DATA_HOME
+    #[arg(long, global = true)]
+    pub store: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// Human readable output
+    Text,
+    /// A single JSON document on stdout, or on stderr for errors
+    Json,
 }
 
 #[derive(Subcommand, Clone)]
@@ -45,4 +77,39 @@ pub enum ConfigSubCommands {
         #[command(subcommand)]
         action: GitActions,
     },
+
+    /// Shows how the live files differ from the current theme
+    Status {
+        device: Option<String>,
+        /// Checks the targets below this directory instead of `/`
+        ///
+        /// The device is not detected from this machine and its packages are not checked.
+        #[arg(long)]
+        target_root: Option<PathBuf>,
+    },
+
+    Variable {
+        #[command(subcommand)]
+        action: VariableActions,
+    },
+
+    Secret {
+        #[command(subcommand)]
+        action: SecretActions,
+    },
+
+    Backup {
+        #[command(subcommand)]
+        action: BackupActions,
+    },
+
+    /// Compares a config between two of: live, <theme>, <theme>@<device> and @<device>
+    ///
+    /// Theme sides show what the theme deploys, with secrets decrypted and templates rendered.
+    Diff {
+        config_name: String,
+        from: DiffSide,
+        #[arg(default_value = "live")]
+        to: DiffSide,
+    },
 }
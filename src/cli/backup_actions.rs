@@ -0,0 +1,21 @@
+use clap::Subcommand;
+use std::path::PathBuf;
+
+#[derive(Subcommand, Clone)]
+pub enum BackupActions {
+    /// Lists the files that were backed up before being overwritten or deleted, newest first
+    List,
+    /// Puts the files of a backup back, or only the one at the path
+    Restore { id: String, path: Option<PathBuf> },
+    /// Changes how many backups are kept before the oldest get pruned
+    SetRetention {
+        #[arg(short, long)]
+        keep: Option<usize>,
+        /// Prunes backups older than this many days
+        #[arg(short, long, conflicts_with = "no_max_age")]
+        max_age_days: Option<u64>,
+        /// Keeps backups regardless of their age
+        #[arg(long, default_value_t = false)]
+        no_max_age: bool,
+    },
+}
@@ -1,19 +1,89 @@
 use clap::Subcommand;
 use std::path::PathBuf;
 
+use crate::config::DeployMode;
+use crate::hook::{HookPolicy, HookStage};
+
 #[derive(Subcommand, Clone)]
 pub enum ConfigActions {
-    /// Moves the original config back if no other theme uses a config with the same name
-    Remove {
-        config_name: String,
-    },
-    /// Moves the original config file while replacing it with a symlink
+    /// Removes the config from the theme, leaving a standalone file at its target
+    Remove { config_name: String },
+    /// Stores the config file in the theme and deploys it back according to the mode
     Add {
         config_name: String,
         file: PathBuf,
+        /// Binds the config to a device or a device group
         device_name: Option<String>,
+        /// Defaults to symlink, or copy for secrets
+        #[arg(short, long, value_enum)]
+        mode: Option<DeployMode>,
+        /// Stores the config encrypted in the theme and leaves the original in place
+        #[arg(short, long, default_value_t = false)]
+        secret: bool,
+        /// The file is inside this directory, the config is recorded by its path below it
+        #[arg(long)]
+        target_root: Option<PathBuf>,
+        /// Only applies the config while the condition holds, like `has(sway)`
+        #[arg(long)]
+        when: Option<String>,
+        /// Only tracks the files of the directory matching one of the globs, like `lua/**`
+        #[arg(long)]
+        include: Vec<String>,
+        /// Leaves the entries of the directory matching the glob in place, on top of `.cmignore`
+        #[arg(long)]
+        exclude: Vec<String>,
     },
-    List {
-        device_name: Option<String>,
+    /// Runs a shell command when the config is applied or removed
+    AddHook {
+        config_name: String,
+        stage: HookStage,
+        command: String,
+        #[arg(long, value_enum, default_value_t = HookPolicy::Abort)]
+        on_failure: HookPolicy,
+    },
+    /// Changes how the config is deployed the next time the theme is used
+    SetMode {
+        config_name: String,
+        mode: DeployMode,
+    },
+    /// Only applies the config while the condition holds, leaving it out removes the condition
+    ///
+    /// Conditions call `has(executable)`, `env(NAME)`, `session()`, `gpu()` and `fact(name)`,
+    /// compare with `==`, `!=` or `~=` for a glob and combine with `!`, `&&`, `||` and
+    /// parentheses, like `has(sway) && env(XDG_SESSION_TYPE) == 'wayland'`.
+    SetWhen {
+        config_name: String,
+        when: Option<String>,
+    },
+    /// Records the current mode bits of the deployed files as the ones every deploy restores
+    RecordAttributes {
+        config_name: String,
+        /// Records and restores the owner and group as well
+        #[arg(long, default_value_t = false)]
+        owner: bool,
+    },
+    /// Pulls edits of deployed copies back into the theme
+    Sync {
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        config_name: Option<String>,
+        /// Syncs every config of the theme
+        #[arg(short, long, default_value_t = false)]
+        all: bool,
+        /// Asks for every hunk whether to sync it
+        #[arg(short, long, default_value_t = false)]
+        interactive: bool,
+        /// Only prints what would be synced
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+        /// Shows the decrypted content of secrets in the diffs instead of masking it
+        #[arg(long, default_value_t = false)]
+        show_secrets: bool,
+        #[arg(short, long)]
+        device: Option<String>,
     },
+    /// Replaces the config in the theme with an encrypted copy
+    Encrypt { config_name: String },
+    /// Lists the configs of every layer, or the ones applied for the device and the order
+    /// its lineage is resolved in
+    List { device_name: Option<String> },
 }
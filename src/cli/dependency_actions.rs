@@ -9,6 +9,9 @@ pub enum DependencyActions {
         dependency_name: String,
         /// Links dependency to the config
         config_name: Option<String>,
+        /// Only installs the dependency for a device or a device group
+        #[arg(short, long, conflicts_with = "config_name")]
+        device: Option<String>,
     },
     List {
         /// Lists only the dependencies of the provided config
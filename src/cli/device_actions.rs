@@ -1,8 +1,62 @@
 use clap::Subcommand;
 
+use crate::package_manager::PackageManagerKind;
+
 #[derive(Subcommand, Clone)]
 pub enum DeviceActions {
-    Remove { name: String },
+    /// Registers a device, so it is known before anything is bound to it
+    Add { name: String },
+    /// Removes the device or group and everything bound to it from the theme
+    Remove {
+        name: String,
+        /// Removes the configs bound to it as well, instead of refusing
+        #[arg(short, long, default_value_t = false)]
+        force: bool,
+    },
+    /// Renames the device in every binding of every theme
+    Rename { name: String, new_name: String },
+    /// Shows the configs, dependencies, variables and facts of the device
+    Show { name: String },
+    /// Installs the dependencies the theme has for the device
     Use { name: String },
+    /// Lists the registered devices and the ones something is bound to
     List,
+    /// Sets the package manager used to install dependencies on the device
+    SetPackageManager {
+        name: String,
+        package_manager: PackageManagerKind,
+    },
+    /// Replaces the rules that pick the device when none is given, every rule set has to hold
+    SetMatch {
+        name: String,
+        /// Glob over the hostname, like `work-*`
+        #[arg(long)]
+        hostname: Option<String>,
+        #[arg(long)]
+        machine_id: Option<String>,
+        /// Glob over the distribution id of os-release, like `arch`
+        #[arg(long)]
+        os: Option<String>,
+        /// Glob over the distribution version of os-release
+        #[arg(long)]
+        os_version: Option<String>,
+        #[arg(long)]
+        arch: Option<String>,
+        /// Whether the machine has to have a battery
+        #[arg(long)]
+        battery: Option<bool>,
+    },
+    /// Replaces the devices of a group, anything bound to the group applies to all of them
+    ///
+    /// Leaving out the devices removes the group.
+    SetGroup { name: String, devices: Vec<String> },
+    /// Makes the device inherit everything bound to the parent and the groups of the parent
+    ///
+    /// Leaving out the parent removes the inheritance.
+    SetParent {
+        name: String,
+        parent: Option<String>,
+    },
+    /// Shows which device this machine is detected as and why
+    Current,
 }
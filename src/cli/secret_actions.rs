@@ -0,0 +1,9 @@
+use clap::Subcommand;
+
+#[derive(Subcommand, Clone)]
+pub enum SecretActions {
+    /// Prints the public key of this machine, generating a key pair on first use
+    Key,
+    /// Encrypts future secrets of the current theme to another public key as well
+    AddRecipient { key: String },
+}
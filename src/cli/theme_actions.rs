@@ -1,21 +1,57 @@
 use clap::Subcommand;
+use std::path::PathBuf;
+
+use crate::hook::{HookPolicy, HookStage};
 
 #[derive(Subcommand, Clone)]
 pub enum ThemeActions {
+    /// Deletes the theme, leaving the files it deployed in place
     Remove {
         name: String,
+        /// Removes it from the parents of themes inheriting from it, instead of refusing
+        #[arg(short, long, default_value_t = false)]
+        force: bool,
     },
     Create {
         name: String,
-        /// Creates a new Theme by copying the provided theme as a base
-        base: Option<String>,
+        /// Themes to inherit configs, dependencies and devices from, later ones take precedence
+        parents: Vec<String>,
+    },
+    /// Replaces the themes the theme inherits from
+    SetParents {
+        name: String,
+        parents: Vec<String>,
     },
     /// Links all the used config files to the according folders
     Use {
         name: String,
         #[arg(short, long, default_value_t = false)]
         force: bool,
+        /// Only prints what would be changed without touching the disk
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
         device: Option<String>,
+        /// Deploys below this directory instead of `/`, leaving the current theme as it is
+        ///
+        /// No hooks run and the device is not detected, it has to be given.
+        #[arg(long)]
+        target_root: Option<PathBuf>,
+    },
+    /// Rolls back a theme switch that was interrupted, putting the original files back
+    ///
+    /// Only for when no other theme switch is running.
+    Recover,
+    /// Runs a shell command when the theme is switched to or away from
+    AddHook {
+        name: String,
+        stage: HookStage,
+        command: String,
+        #[arg(long, value_enum, default_value_t = HookPolicy::Abort)]
+        on_failure: HookPolicy,
+    },
+    /// Rewrites themes stored in an older layout as a single theme.toml, all themes if none are given
+    Migrate {
+        names: Vec<String>,
     },
     List,
 }
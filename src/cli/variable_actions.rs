@@ -0,0 +1,26 @@
+use clap::Subcommand;
+
+#[derive(Subcommand, Clone)]
+pub enum VariableActions {
+    /// Sets a template variable for the theme or overrides it for a device
+    Set {
+        name: String,
+        value: String,
+        /// A device or a device group
+        #[arg(short, long)]
+        device: Option<String>,
+        /// Stores the value encrypted
+        #[arg(short, long, default_value_t = false)]
+        secret: bool,
+    },
+    Remove {
+        name: String,
+        #[arg(short, long)]
+        device: Option<String>,
+    },
+    /// Lists the resolved variables including the built-in facts
+    List {
+        #[arg(short, long)]
+        device: Option<String>,
+    },
+}
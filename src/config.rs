@@ -1,15 +1,58 @@
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
+use crate::backup::Backup;
 use crate::dependency::Dependency;
+use crate::diff::collect_files;
+use crate::error::ConfigCliError;
+use crate::hook::Hooks;
+use crate::predicate::Predicate;
+use crate::secret::decrypt_file;
+use crate::template::render;
+use crate::utils::ConfigResult;
+use crate::utils::{copy_dir_all, reroot};
+use crate::{try_copy_recursive, try_delete, try_read_file};
 
 pub mod add;
+pub mod add_hook;
+pub mod attributes;
+pub mod encrypt;
+pub mod filter;
 pub mod list;
+pub mod record_attributes;
 pub mod remove;
+pub mod set_mode;
+pub mod set_when;
+pub mod sync;
 
 pub use add::*;
+pub use add_hook::*;
+pub use attributes::*;
+pub use encrypt::*;
+pub use filter::*;
 pub use list::*;
+pub use record_attributes::*;
 pub use remove::*;
+pub use set_mode::*;
+pub use set_when::*;
+pub use sync::*;
+
+/// How a config gets from the theme store to its target
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployMode {
+    /// The target links to the theme store, edits land in the theme directly
+    #[default]
+    Symlink,
+    /// The target is an independent copy, for programs that replace their config atomically
+    Copy,
+    /// The target shares the file with the theme store, both have to be on the same filesystem
+    Hardlink,
+    /// The target is rendered with the theme variables
+    Template,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Config {
@@ -18,10 +61,217 @@ pub struct Config {
     pub symlink: PathBuf,
     pub conf_location: PathBuf,
     pub active: bool,
+    #[serde(default)]
+    pub mode: DeployMode,
+    // Written before the deploy modes existed, read as the template mode
+    #[serde(default, skip_serializing)]
+    template: bool,
+    /// Stored age encrypted in the theme and decrypted when applied
+    #[serde(default)]
+    pub secret: bool,
+    /// Run when the config is applied or removed
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// Only applied while the condition holds, see [`Predicate`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    /// Globs picking the files of a directory config that are tracked, see [`EntryFilter`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Globs of entries that stay out of the theme, on top of the ones in its `.cmignore`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Restored on every deploy and checked by `status`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<FileAttributes>,
+}
+
+impl Config {
+    pub fn new(name: String, symlink: PathBuf, conf_location: PathBuf, mode: DeployMode) -> Self {
+        Config {
+            name,
+            dependencies: vec![],
+            symlink,
+            conf_location,
+            active: false,
+            mode,
+            template: false,
+            secret: false,
+            hooks: Hooks::default(),
+            when: None,
+            include: vec![],
+            exclude: vec![],
+            attributes: vec![],
+        }
+    }
+
+    /// The filter of a directory config that only tracks some of its entries
+    pub fn entry_filter(&self) -> ConfigResult<Option<EntryFilter>> {
+        EntryFilter::for_dir(&self.conf_location, &self.include, &self.exclude)
+    }
+
+    /// The tracked files of a filtered directory config, nothing if it is deployed as a whole
+    pub fn tracked_entries(&self) -> ConfigResult<Option<Vec<PathBuf>>> {
+        let Some(filter) = self.entry_filter()? else {
+            return Ok(None);
+        };
+        let mut files = BTreeSet::new();
+        collect_files(
+            &self.conf_location,
+            Path::new(""),
+            &mut files,
+            Some(&filter),
+        )?;
+        Ok(Some(files.into_iter().collect()))
+    }
+
+    /// A file of a directory config as a config of its own
+    pub fn entry(&self, relative: &Path) -> Config {
+        let mut entry = self.clone();
+        entry.symlink = self.symlink.join(relative);
+        entry.conf_location = self.conf_location.join(relative);
+        entry.include.clear();
+        entry.exclude.clear();
+        entry.attributes = attributes_below(&self.attributes, relative);
+        entry
+    }
+
+    /// Where the config is deployed, moved below the root if one is given
+    pub fn target(&self, root: Option<&Path>) -> PathBuf {
+        match root {
+            Some(root) => reroot(root, &self.symlink),
+            None => self.symlink.clone(),
+        }
+    }
+
+    /// What a symlinked target points to, below a root that is a mirror of the store content
+    pub fn link_source(&self, root: Option<&Path>) -> PathBuf {
+        match root {
+            Some(root) => reroot(root, &self.conf_location),
+            None => self.conf_location.clone(),
+        }
+    }
+
+    /// Why the config is left out on this machine, nothing if it has no condition or it holds
+    pub fn skip_reason(&self) -> ConfigResult<Option<String>> {
+        let Some(when) = &self.when else {
+            return Ok(None);
+        };
+        let evaluation = Predicate::parse(when)?.evaluate()?;
+        match evaluation.holds {
+            true => Ok(None),
+            false => Ok(Some(evaluation.observed.join(", "))),
+        }
+    }
+
+    /// The mode the config is actually deployed with
+    pub fn deploy_mode(&self) -> DeployMode {
+        match (self.template, self.secret, self.mode) {
+            (true, _, _) => DeployMode::Template,
+            // Secrets only exist decrypted at the target, so there is nothing in the store to link to
+            (_, true, DeployMode::Symlink | DeployMode::Hardlink) => DeployMode::Copy,
+            (_, _, mode) => mode,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: DeployMode) -> ConfigResult<()> {
+        self.check_mode(mode, self.conf_location.is_dir())?;
+        self.mode = mode;
+        self.template = false;
+        Ok(())
+    }
+
+    /// Fails if the config can't be deployed with the mode, directories can only be linked or copied
+    pub fn check_mode(&self, mode: DeployMode, is_dir: bool) -> ConfigResult<()> {
+        let invalid = match mode {
+            DeployMode::Symlink => self.secret,
+            DeployMode::Hardlink => self.secret || is_dir,
+            DeployMode::Template => is_dir,
+            DeployMode::Copy => false,
+        };
+        if invalid {
+            return Err(ConfigCliError::InvalidDeployMode(format!(
+                "{} can't be deployed as {:?}",
+                self.name, mode
+            )));
+        }
+        Ok(())
+    }
+
+    /// Leaves a standalone file at the target, so it survives the config leaving the theme
+    ///
+    /// Only the tracked files of a filtered directory are touched.
+    pub fn detach(&self, backup: &mut Backup) -> ConfigResult<()> {
+        if let Some(entries) = self.tracked_entries()? {
+            for relative in entries {
+                self.entry(&relative).detach(backup)?;
+            }
+            return Ok(());
+        }
+
+        let links_to_store = std::fs::read_link(&self.symlink)
+            .map(|x| x == self.conf_location)
+            .unwrap_or(false);
+        if !links_to_store && self.symlink.exists() {
+            return Ok(());
+        }
+
+        if links_to_store {
+            backup.snapshot(&self.symlink)?;
+            try_delete!(self.symlink.clone());
+        }
+        // Copies, hardlinks and rendered files are already standalone unless they went missing
+        if self.secret {
+            return Ok(());
+        }
+        try_copy_recursive!(self.conf_location.clone(), self.symlink.clone());
+        Ok(())
+    }
+
+    /// Content written to the target instead of copying `conf_location`, for secrets and templates
+    pub fn generated_content(
+        &self,
+        variables: &BTreeMap<String, String>,
+    ) -> ConfigResult<Option<Vec<u8>>> {
+        if !self.secret && self.deploy_mode() != DeployMode::Template {
+            return Ok(None);
+        }
+        if self.conf_location.is_dir() {
+            return Err(ConfigCliError::InvalidConfigLocation(
+                self.conf_location.to_string_lossy().to_string(),
+            ));
+        }
+
+        let content = match self.secret {
+            true => decrypt_file(&self.conf_location)?,
+            false => try_read_file!(&self.conf_location),
+        };
+        if self.deploy_mode() != DeployMode::Template {
+            return Ok(Some(content));
+        }
+
+        let template = match String::from_utf8(content) {
+            Ok(template) => template,
+            Err(err) => return Err(ConfigCliError::StringConversionError(err.utf8_error())),
+        };
+        Ok(Some(render(&template, variables)?.into_bytes()))
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub struct ConfigFile {
+    #[serde(default)]
     pub globals: Vec<Config>,
+    #[serde(default)]
     pub device_bounds: Vec<(String, Config)>,
 }
+
+impl ConfigFile {
+    /// The global config with the name and all of its device bound variants
+    pub fn named_mut<'a>(&'a mut self, name: &'a str) -> impl Iterator<Item = &'a mut Config> {
+        self.globals
+            .iter_mut()
+            .chain(self.device_bounds.iter_mut().map(|x| &mut x.1))
+            .filter(move |x| x.name == name)
+    }
+}
@@ -1,70 +1,182 @@
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 
-use super::{Config, ConfigFile};
+use super::{read_attributes, Config, DeployMode, EntryFilter, EntryGlobs};
+use crate::diff::collect_files;
 use crate::error::ConfigCliError;
-use crate::utils::{get_base_dir, ConfigResult};
-use crate::{try_read_and_parse, try_rename, try_symlink, try_write_file};
+use crate::predicate::Predicate;
+use crate::secret::encrypt;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::{
+    copy_dir_all, get_base_dir, get_current_theme, relative_link, reroot, ConfigResult,
+};
+use crate::{try_copy_recursive, try_read_file, try_rename, try_symlink};
 
+/// How the config is deployed, as given on the command line
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions {
+    pub mode: Option<DeployMode>,
+    pub secret: bool,
+    pub when: Option<String>,
+}
+
+/// Stores the file in the current theme and deploys it back according to the mode
+///
+/// Of a directory with globs or a `.cmignore` only the tracked files move into the store, the
+/// entries left out stay where they are.
 pub fn add_config(
     name: String,
     device: Option<String>,
-    theme: String,
     file: PathBuf,
+    options: AddOptions,
+    target_root: Option<PathBuf>,
+    globs: EntryGlobs,
 ) -> ConfigResult<()> {
+    let AddOptions { mode, secret, when } = options;
+    // The condition is checked up front, so a typo doesn't leave the config added
+    if let Some(when) = &when {
+        Predicate::parse(when)?;
+    }
+
+    let theme = get_current_theme()?;
+    let mode = match (mode, secret) {
+        (Some(mode), _) => mode,
+        (None, true) => DeployMode::Copy,
+        (None, false) => DeployMode::Symlink,
+    };
+
     let theme_path = get_base_dir()? + &theme;
 
+    // Below a target root the file is recorded by its path inside the root, given either way
+    let (file, live) = match &target_root {
+        Some(root) => {
+            let inside = match file.strip_prefix(root) {
+                Ok(inside) => Path::new("/").join(inside),
+                Err(_) => file,
+            };
+            let live = reroot(root, &inside);
+            (inside, live)
+        }
+        None => (file.clone(), file),
+    };
+
     if !Path::new(&theme_path).exists() {
         return Err(ConfigCliError::InvalidThemeName(theme));
     }
 
-    let config_file_path = theme_path.clone() + "/configs.toml";
-
-    let config_file = try_read_and_parse!(config_file_path.clone(), ConfigFile);
+    let mut manifest = load_manifest(&theme)?;
+    let config_file = manifest.configs.clone();
     let mut config_file_clone = config_file.clone();
 
-    let global_matching_configs: Vec<Config> = config_file
-        .globals
-        .into_iter()
-        .filter(|conf| conf.name == name)
-        .collect();
-
-    let device_matching_configs: Vec<(String, Config)> = if device.is_some() {
-        config_file
+    // A device bound config is a variant of the global one with the same name
+    let name_used = match &device {
+        Some(device) => config_file
             .device_bounds
-            .into_iter()
-            .filter(|conf| conf.0 == device.clone().unwrap() && conf.1.name == name)
-            .collect()
-    } else {
-        vec![]
+            .iter()
+            .any(|conf| &conf.0 == device && conf.1.name == name),
+        None => config_file.globals.iter().any(|conf| conf.name == name),
     };
-
-    if global_matching_configs.len() > 0 || device_matching_configs.len() > 0 {
+    if name_used {
         return Err(ConfigCliError::InvalidConfigName(name));
     }
 
-    let link_string = theme_path.clone() + "/" + &name.clone();
+    // Device variants are stored next to the global config under their own name
+    let stored_name = match &device {
+        Some(device) => name.clone() + "@" + device,
+        None => name.clone(),
+    };
+    // Secrets are stored encrypted, so the live file stays where it is
+    let link_string = match secret {
+        true => theme_path.clone() + "/" + &stored_name + ".age",
+        false => theme_path.clone() + "/" + &stored_name,
+    };
     let link_path = Path::new(&link_string);
+    // A renamed device keeps the stored files of its variants under the old name
+    if std::fs::symlink_metadata(link_path).is_ok() {
+        return Err(ConfigCliError::InvalidConfigName(name));
+    }
 
-    let new_conf = Config {
-        name,
-        dependencies: vec![],
-        symlink: file.clone(),
-        conf_location: link_path.to_path_buf(),
-        active: false,
-    };
+    if secret && live.is_dir() {
+        return Err(ConfigCliError::InvalidConfigLocation(
+            file.to_string_lossy().to_string(),
+        ));
+    }
 
-    if device.is_some() {
-        config_file_clone
-            .device_bounds
-            .push((device.unwrap(), new_conf));
+    if !globs.is_empty() && !live.is_dir() {
+        return Err(ConfigCliError::InvalidPattern(format!(
+            "{} is not a directory, only entries of directories are filtered",
+            file.display()
+        )));
+    }
+    let filter = EntryFilter::for_dir(&live, &globs.include, &globs.exclude)?;
+
+    let mut new_conf = Config::new(name, file.clone(), link_path.to_path_buf(), mode);
+    new_conf.secret = secret;
+    new_conf.when = when;
+    new_conf.include = globs.include;
+    new_conf.exclude = globs.exclude;
+    new_conf.attributes = read_attributes(&live, false, filter.as_ref())?;
+    new_conf.check_mode(mode, live.is_dir())?;
+
+    match device {
+        Some(device) => config_file_clone.device_bounds.push((device, new_conf)),
+        None => config_file_clone.globals.push(new_conf),
+    }
+
+    if secret {
+        let ciphertext = encrypt(&theme, &try_read_file!(&live))?;
+        match std::fs::write(link_path, ciphertext) {
+            Ok(_) => (),
+            Err(err) => return Err(ConfigCliError::FsWriteError(err).at(link_path)),
+        }
+    } else if let Some(filter) = &filter {
+        let mut files = BTreeSet::new();
+        collect_files(&live, Path::new(""), &mut files, Some(filter))?;
+        for relative in files {
+            let stored = link_path.join(&relative);
+            if let Some(parent) = stored.parent() {
+                if let Err(err) = std::fs::create_dir_all(parent) {
+                    return Err(ConfigCliError::FileCreationError(err).at(parent));
+                }
+            }
+            store_path(&live.join(&relative), &stored, mode, target_root.as_deref())?;
+        }
     } else {
-        config_file_clone.globals.push(new_conf);
+        store_path(&live, link_path, mode, target_root.as_deref())?;
     }
 
-    try_rename!(file.clone(), link_path);
-    try_symlink!(link_path, file);
+    manifest.configs = config_file_clone;
+    save_manifest(&theme, &manifest)?;
 
-    try_write_file!(theme_path, &config_file_clone);
+    Ok(())
+}
 
+// Moves or copies the live path into the store and deploys it back according to the mode
+fn store_path(
+    live: &Path,
+    stored: &Path,
+    mode: DeployMode,
+    target_root: Option<&Path>,
+) -> ConfigResult<()> {
+    match mode {
+        DeployMode::Symlink => {
+            try_rename!(live, stored);
+            match target_root {
+                // The link points to a mirror of the store inside the root, like `theme use` does
+                Some(root) => {
+                    let store_copy = reroot(root, stored);
+                    try_copy_recursive!(stored, &store_copy);
+                    try_symlink!(relative_link(live, &store_copy), live);
+                }
+                None => try_symlink!(stored, live),
+            }
+        }
+        // The live file stays in place as the deployed copy
+        DeployMode::Copy | DeployMode::Template => try_copy_recursive!(live, stored),
+        DeployMode::Hardlink => match std::fs::hard_link(live, stored) {
+            Ok(_) => (),
+            Err(err) => return Err(ConfigCliError::SymlinkError(err).at(live)),
+        },
+    }
     Ok(())
 }
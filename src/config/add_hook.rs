@@ -0,0 +1,26 @@
+use crate::error::ConfigCliError;
+use crate::hook::{Hook, HookStage};
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::ConfigResult;
+
+/// Adds the hook to every config of the theme with the name, global and device bound
+pub fn add_config_hook(
+    name: String,
+    theme: String,
+    stage: HookStage,
+    hook: Hook,
+) -> ConfigResult<()> {
+    let mut manifest = load_manifest(&theme)?;
+
+    let mut found = false;
+    for config in manifest.configs.named_mut(&name) {
+        found = true;
+        config.hooks.stage_mut(stage).push(hook.clone());
+    }
+
+    if !found {
+        return Err(ConfigCliError::InvalidConfigName(name));
+    }
+
+    save_manifest(&theme, &manifest)
+}
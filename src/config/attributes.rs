@@ -0,0 +1,201 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use super::EntryFilter;
+use crate::diff::join_relative;
+use crate::error::ConfigCliError;
+use crate::utils::ConfigResult;
+
+/// Mode bits and optionally the owner of a file of a config, restored whenever it is deployed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileAttributes {
+    /// Relative to the config, empty for the config itself
+    #[serde(default, skip_serializing_if = "is_empty_path")]
+    pub path: PathBuf,
+    /// Written in octal, like `"0600"`
+    #[serde(
+        serialize_with = "serialize_mode",
+        deserialize_with = "deserialize_mode"
+    )]
+    pub mode: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+}
+
+/// A file whose mode or owner differs from what the config recorded
+#[derive(Debug, Clone, Serialize)]
+pub struct AttributeDrift {
+    pub path: PathBuf,
+    pub expected: String,
+    pub actual: String,
+}
+
+fn is_empty_path(path: &Path) -> bool {
+    path.as_os_str().is_empty()
+}
+
+pub fn format_mode(mode: u32) -> String {
+    format!("{:04o}", mode)
+}
+
+fn serialize_mode<S: Serializer>(mode: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_mode(*mode))
+}
+
+fn deserialize_mode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let mode = String::deserialize(deserializer)?;
+    u32::from_str_radix(&mode, 8).map_err(serde::de::Error::custom)
+}
+
+// Only the permission bits, the file type is given by what is deployed
+const MODE_BITS: u32 = 0o7777;
+
+/// Reads the attributes of the path and of every file and directory below it the filter keeps
+///
+/// Links and special files are skipped, the owner is only recorded if asked for. Of a filtered
+/// directory only the files are recorded.
+pub fn read_attributes(
+    root: &Path,
+    owner: bool,
+    filter: Option<&EntryFilter>,
+) -> ConfigResult<Vec<FileAttributes>> {
+    let mut attributes = vec![];
+    collect_attributes(root, Path::new(""), owner, filter, &mut attributes)?;
+    Ok(attributes)
+}
+
+fn collect_attributes(
+    root: &Path,
+    relative: &Path,
+    owner: bool,
+    filter: Option<&EntryFilter>,
+    attributes: &mut Vec<FileAttributes>,
+) -> ConfigResult<()> {
+    let path = join_relative(root, relative);
+    let meta = match std::fs::symlink_metadata(&path) {
+        Ok(meta) => meta,
+        Err(err) => return Err(ConfigCliError::FsReadError(err).at(&path)),
+    };
+    if !meta.is_dir() && !meta.is_file() {
+        return Ok(());
+    }
+    // The directories of a filtered config are shared with what it leaves out, so they are left be
+    if meta.is_file() || filter.is_none() {
+        attributes.push(FileAttributes {
+            path: relative.to_path_buf(),
+            mode: meta.mode() & MODE_BITS,
+            uid: owner.then_some(meta.uid()),
+            gid: owner.then_some(meta.gid()),
+        });
+    }
+    if !meta.is_dir() {
+        return Ok(());
+    }
+
+    let entries = match std::fs::read_dir(&path) {
+        Ok(entries) => entries,
+        Err(err) => return Err(ConfigCliError::FsReadError(err).at(&path)),
+    };
+    let mut names = vec![];
+    for entry in entries {
+        match entry {
+            Ok(entry) => names.push(entry.file_name()),
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(&path)),
+        }
+    }
+    names.sort();
+    for name in names {
+        let entry = relative.join(name);
+        let is_dir = std::fs::symlink_metadata(root.join(&entry)).is_ok_and(|x| x.is_dir());
+        let kept = match (filter, is_dir) {
+            (None, _) => true,
+            (Some(filter), true) => filter.keeps_dir(&entry),
+            (Some(filter), false) => filter.keeps_file(&entry),
+        };
+        if kept {
+            collect_attributes(root, &entry, owner, filter, attributes)?;
+        }
+    }
+    Ok(())
+}
+
+/// The attributes of the files below the relative path, relative to it in turn
+pub fn attributes_below(attributes: &[FileAttributes], relative: &Path) -> Vec<FileAttributes> {
+    attributes
+        .iter()
+        .filter_map(|x| {
+            let path = x.path.strip_prefix(relative).ok()?;
+            Some(FileAttributes {
+                path: path.to_path_buf(),
+                ..x.clone()
+            })
+        })
+        .collect()
+}
+
+/// Sets the recorded attributes on the path and below it, missing files are left out
+pub fn restore_attributes(root: &Path, attributes: &[FileAttributes]) -> ConfigResult<()> {
+    for attribute in attributes {
+        let path = join_relative(root, &attribute.path);
+        let is_link = match std::fs::symlink_metadata(&path) {
+            Ok(meta) => meta.is_symlink(),
+            Err(_) => continue,
+        };
+        if is_link {
+            continue;
+        }
+
+        let permissions = std::fs::Permissions::from_mode(attribute.mode);
+        if let Err(err) = std::fs::set_permissions(&path, permissions) {
+            return Err(ConfigCliError::FsWriteError(err).at(&path));
+        }
+        if attribute.uid.is_some() || attribute.gid.is_some() {
+            if let Err(err) = std::os::unix::fs::chown(&path, attribute.uid, attribute.gid) {
+                return Err(ConfigCliError::FsWriteError(err).at(&path));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Every file at or below the path whose mode or owner is not the recorded one
+///
+/// Links are followed, so a config deployed as a link is checked at the store.
+pub fn attribute_drift(
+    root: &Path,
+    attributes: &[FileAttributes],
+) -> ConfigResult<Vec<AttributeDrift>> {
+    let mut drift = vec![];
+    for attribute in attributes {
+        let path = join_relative(root, &attribute.path);
+        let Ok(meta) = std::fs::metadata(&path) else {
+            continue;
+        };
+
+        let mode = meta.mode() & MODE_BITS;
+        if mode != attribute.mode {
+            drift.push(AttributeDrift {
+                path: path.clone(),
+                expected: format_mode(attribute.mode),
+                actual: format_mode(mode),
+            });
+        }
+        let owner_differs = attribute.uid.is_some_and(|x| x != meta.uid())
+            || attribute.gid.is_some_and(|x| x != meta.gid());
+        if owner_differs {
+            let owner = |uid: Option<u32>, gid: Option<u32>| {
+                let show = |id: Option<u32>| id.map(|x| x.to_string()).unwrap_or("*".to_owned());
+                format!("{}:{}", show(uid), show(gid))
+            };
+            drift.push(AttributeDrift {
+                expected: owner(attribute.uid, attribute.gid),
+                actual: owner(Some(meta.uid()), Some(meta.gid())),
+                path,
+            });
+        }
+    }
+    Ok(drift)
+}
@@ -0,0 +1,67 @@
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+
+use super::DeployMode;
+use crate::error::ConfigCliError;
+use crate::secret::encrypt;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::ConfigResult;
+use crate::{try_delete, try_read_file};
+
+/// Marks a config of the theme as secret, replacing its plaintext in the theme with an encrypted copy
+pub fn encrypt_config(name: String, theme: String) -> ConfigResult<()> {
+    let mut manifest = load_manifest(&theme)?;
+    let configs = manifest.configs.named_mut(&name).filter(|x| !x.secret);
+
+    let mut found = false;
+    for config in configs {
+        found = true;
+        if config.conf_location.is_dir() {
+            return Err(ConfigCliError::InvalidConfigLocation(
+                config.conf_location.to_string_lossy().to_string(),
+            ));
+        }
+
+        let plaintext = try_read_file!(&config.conf_location);
+        let mut encrypted_location = config.conf_location.clone().into_os_string();
+        encrypted_location.push(".age");
+        let encrypted_location = PathBuf::from(encrypted_location);
+
+        match std::fs::write(&encrypted_location, encrypt(&theme, &plaintext)?) {
+            Ok(_) => (),
+            Err(err) => return Err(ConfigCliError::FsWriteError(err).at(&encrypted_location)),
+        }
+        try_delete!(&config.conf_location);
+
+        // The live link pointed at the plaintext, keep the file usable until the theme is applied again
+        let links_to_plaintext = std::fs::read_link(&config.symlink)
+            .map(|x| x == config.conf_location)
+            .unwrap_or(false);
+        if links_to_plaintext {
+            try_delete!(&config.symlink);
+            let written = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&config.symlink)
+                .and_then(|mut file| file.write_all(&plaintext));
+            if let Err(err) = written {
+                return Err(ConfigCliError::FsWriteError(err).at(&config.symlink));
+            }
+        }
+
+        config.conf_location = encrypted_location;
+        config.secret = true;
+        if config.deploy_mode() != DeployMode::Template {
+            config.mode = DeployMode::Copy;
+        }
+    }
+
+    if !found {
+        return Err(ConfigCliError::InvalidConfigName(name));
+    }
+
+    save_manifest(&theme, &manifest)?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+use crate::error::ConfigCliError;
+use crate::utils::ConfigResult;
+
+/// Patterns read from the root of a directory config, added to its excludes
+pub const IGNORE_FILE: &str = ".cmignore";
+
+/// The globs of a directory config as given on the command line
+#[derive(Debug, Clone, Default)]
+pub struct EntryGlobs {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl EntryGlobs {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+}
+
+/// Which entries of a directory config belong to the theme
+///
+/// Globs match the path relative to the directory, where `*` also matches across `/`. An excluded
+/// directory is skipped with everything below it, includes only pick files. Anything that is not
+/// a regular file, like sockets or links, is never tracked.
+#[derive(Debug, Clone)]
+pub struct EntryFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl EntryFilter {
+    /// The filter of the directory, nothing if it tracks every entry
+    pub fn for_dir(
+        dir: &Path,
+        include: &[String],
+        exclude: &[String],
+    ) -> ConfigResult<Option<Self>> {
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+        let mut exclude = exclude.to_vec();
+        exclude.extend(read_ignore_file(dir)?);
+        if include.is_empty() && exclude.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(EntryFilter {
+            include: match include.is_empty() {
+                true => None,
+                false => Some(glob_set(include)?),
+            },
+            exclude: glob_set(&exclude)?,
+        }))
+    }
+
+    pub fn keeps_dir(&self, relative: &Path) -> bool {
+        !self.exclude.is_match(relative)
+    }
+
+    pub fn keeps_file(&self, relative: &Path) -> bool {
+        self.keeps_dir(relative)
+            && self
+                .include
+                .as_ref()
+                .is_none_or(|include| include.is_match(relative))
+    }
+}
+
+fn glob_set(patterns: &[String]) -> ConfigResult<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        match Glob::new(pattern.trim_end_matches('/')) {
+            Ok(glob) => builder.add(glob),
+            Err(err) => return Err(ConfigCliError::InvalidPattern(err.to_string())),
+        };
+    }
+    match builder.build() {
+        Ok(set) => Ok(set),
+        Err(err) => Err(ConfigCliError::InvalidPattern(err.to_string())),
+    }
+}
+
+// One pattern per line, blank lines and lines starting with `#` are skipped
+fn read_ignore_file(dir: &Path) -> ConfigResult<Vec<String>> {
+    let path = dir.join(IGNORE_FILE);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(ConfigCliError::FsReadError(err).at(&path)),
+    };
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(str::to_owned)
+        .collect())
+}
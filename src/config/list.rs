@@ -1,27 +1,28 @@
-use super::{Config, ConfigFile};
-use crate::error::ConfigCliError;
-use crate::{get_base_dir, try_read_and_parse, ConfigResult};
+use serde::Serialize;
 
-pub fn list_configs(theme: String, device: Option<String>) -> ConfigResult<Vec<Config>> {
-    let theme = get_base_dir()? + &theme + "/configs.toml";
-    let config_file = try_read_and_parse!(theme, ConfigFile);
+use crate::device::device_lineage;
+use crate::theme::{effective_configs, layered_configs, EffectiveConfig};
+use crate::ConfigResult;
 
-    if device.is_some() {
-        Ok(config_file
-            .device_bounds
-            .iter()
-            .filter(|x| x.0 == device.clone().unwrap())
-            .map(|x| x.1.clone())
-            .collect())
-    } else {
-        let mut all_configs: Vec<Config> = config_file.globals;
-        all_configs.extend(
-            config_file
-                .device_bounds
-                .into_iter()
-                .map(|x| x.1)
-                .collect::<Vec<Config>>(),
-        );
-        Ok(all_configs)
-    }
+/// The configs applied for a device and the order they are resolved in
+#[derive(Debug, Serialize)]
+pub struct DeviceConfigs {
+    pub device: String,
+    /// Groups and devices configs are bound to, after the theme wide configs and least specific
+    /// first
+    pub lineage: Vec<String>,
+    pub configs: Vec<EffectiveConfig>,
+}
+
+/// Lists the configs resolved through the inheritance chain together with the theme they come from
+pub fn list_configs(theme: String) -> ConfigResult<Vec<EffectiveConfig>> {
+    layered_configs(&theme)
+}
+
+pub fn list_device_configs(theme: String, device: String) -> ConfigResult<DeviceConfigs> {
+    Ok(DeviceConfigs {
+        lineage: device_lineage(&theme, &device)?,
+        configs: effective_configs(&theme, Some(&device))?,
+        device,
+    })
 }
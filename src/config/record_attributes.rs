@@ -0,0 +1,28 @@
+use super::{read_attributes, DeployMode};
+use crate::error::ConfigCliError;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::ConfigResult;
+
+/// Records the current mode bits, and the owner if asked for, as the ones every deploy restores
+///
+/// They are read from the deployed files, or from the store for configs deployed as links.
+pub fn record_config_attributes(name: String, theme: String, owner: bool) -> ConfigResult<()> {
+    let mut manifest = load_manifest(&theme)?;
+
+    let mut found = false;
+    for config in manifest.configs.named_mut(&name) {
+        found = true;
+        let source = match config.deploy_mode() {
+            DeployMode::Symlink => config.conf_location.clone(),
+            _ => config.symlink.clone(),
+        };
+        let filter = config.entry_filter()?;
+        config.attributes = read_attributes(&source, owner, filter.as_ref())?;
+    }
+
+    if !found {
+        return Err(ConfigCliError::InvalidConfigName(name));
+    }
+
+    save_manifest(&theme, &manifest)
+}
@@ -1,20 +1,13 @@
-use std::path::Path;
-
-use super::{Config, ConfigFile};
+use super::Config;
+use crate::backup::Backup;
 use crate::error::ConfigCliError;
-use crate::utils::{get_base_dir, ConfigResult};
-use crate::{copy_dir_all, try_copy_recursive, try_delete, try_read_and_parse, try_write_file};
-
-pub fn remove_config(name: String, theme: String) -> ConfigResult<()> {
-    let theme_path = get_base_dir()? + &theme;
-
-    if !Path::new(&theme_path).exists() {
-        return Err(ConfigCliError::InvalidThemeName(theme));
-    }
-
-    let config_file_path = theme_path.clone() + "/configs.toml";
+use crate::hook::{run_hooks, HookContext, HookRun, HookStage};
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::ConfigResult;
 
-    let config_file = try_read_and_parse!(config_file_path.clone(), ConfigFile);
+pub fn remove_config(name: String, theme: String) -> ConfigResult<Vec<HookRun>> {
+    let mut manifest = load_manifest(&theme)?;
+    let config_file = manifest.configs.clone();
     let mut config_file_clone = config_file.clone();
 
     let mut all_configs: Vec<Config> = config_file.globals;
@@ -23,15 +16,24 @@ pub fn remove_config(name: String, theme: String) -> ConfigResult<()> {
     let config_to_remove: &Config =
         all_configs
             .iter()
-            .filter(|conf| conf.name == name)
-            .last()
+            .rfind(|conf| conf.name == name)
             .ok_or::<ConfigCliError>(ConfigCliError::InvalidConfigName(name.clone()))?;
 
-    try_delete!(config_to_remove.symlink.clone());
-    try_copy_recursive!(
-        config_to_remove.conf_location.clone(),
-        config_to_remove.symlink.clone()
-    );
+    let context = HookContext {
+        theme: &theme,
+        config: Some((&name, &config_to_remove.symlink)),
+    };
+    let mut runs = vec![];
+    run_hooks(
+        &config_to_remove.hooks,
+        HookStage::PreRemove,
+        &context,
+        &mut runs,
+    )?;
+
+    let mut backup = Backup::begin(&format!("config remove {}", name))?;
+    config_to_remove.detach(&mut backup)?;
+    backup.finish()?;
 
     config_file_clone
         .globals
@@ -40,7 +42,14 @@ pub fn remove_config(name: String, theme: String) -> ConfigResult<()> {
         .device_bounds
         .retain(|conf| conf.1.name != name);
 
-    try_write_file!(config_file_path, &config_file_clone);
+    manifest.configs = config_file_clone;
+    save_manifest(&theme, &manifest)?;
 
-    Ok(())
+    run_hooks(
+        &config_to_remove.hooks,
+        HookStage::PostRemove,
+        &context,
+        &mut runs,
+    )?;
+    Ok(runs)
 }
@@ -0,0 +1,21 @@
+use super::DeployMode;
+use crate::error::ConfigCliError;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::ConfigResult;
+
+/// Changes how the config is deployed, taking effect the next time the theme is used
+pub fn set_config_mode(name: String, theme: String, mode: DeployMode) -> ConfigResult<()> {
+    let mut manifest = load_manifest(&theme)?;
+
+    let mut found = false;
+    for config in manifest.configs.named_mut(&name) {
+        found = true;
+        config.set_mode(mode)?;
+    }
+
+    if !found {
+        return Err(ConfigCliError::InvalidConfigName(name));
+    }
+
+    save_manifest(&theme, &manifest)
+}
@@ -0,0 +1,25 @@
+use crate::error::ConfigCliError;
+use crate::predicate::Predicate;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::ConfigResult;
+
+/// Makes the config only apply while the condition holds, no condition applies it always
+pub fn set_config_when(name: String, theme: String, when: Option<String>) -> ConfigResult<()> {
+    if let Some(when) = &when {
+        Predicate::parse(when)?;
+    }
+
+    let mut manifest = load_manifest(&theme)?;
+
+    let mut found = false;
+    for config in manifest.configs.named_mut(&name) {
+        found = true;
+        config.when = when.clone();
+    }
+
+    if !found {
+        return Err(ConfigCliError::InvalidConfigName(name));
+    }
+
+    save_manifest(&theme, &manifest)
+}
@@ -0,0 +1,222 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::{DeployMode, EntryFilter};
+use crate::device::resolve_device;
+use crate::diff::{
+    apply_hunks, collect_files, colorize, hunks, join_relative, mask_diff, unified_diff,
+};
+use crate::error::ConfigCliError;
+use crate::secret::{decrypt_file, encrypt};
+use crate::theme::{effective_configs, EffectiveConfig};
+use crate::utils::ConfigResult;
+use crate::{try_delete, try_read_file};
+
+/// A file in the theme store that took over edits from its deployed copy
+#[derive(Debug, Serialize)]
+pub struct SyncedFile {
+    pub config: String,
+    pub path: PathBuf,
+    /// The edits that were pulled back, as a unified diff
+    pub diff: String,
+}
+
+// How the hunks of the remaining files get picked
+enum Selection {
+    All,
+    Interactive,
+    Quit,
+}
+
+/// Pulls edits of deployed copies back into the theme store
+///
+/// Links already edit the store directly and rendered templates can't be turned back into the template,
+/// so only copies, hardlinks and links that got replaced by a file are synced. The diffs of secrets
+/// are masked unless they are asked for.
+pub fn sync_configs(
+    theme: String,
+    name: Option<String>,
+    device: Option<String>,
+    interactive: bool,
+    dry_run: bool,
+    show_secrets: bool,
+) -> ConfigResult<Vec<SyncedFile>> {
+    let device = resolve_device(&theme, device)?;
+    let configs: Vec<EffectiveConfig> = effective_configs(&theme, device.as_deref())?
+        .into_iter()
+        .filter(|x| name.is_none() || name.as_ref() == Some(&x.config.name))
+        .collect();
+
+    if let Some(name) = &name {
+        if configs.is_empty() {
+            return Err(ConfigCliError::InvalidConfigName(name.clone()));
+        }
+        if configs[0].config.deploy_mode() == DeployMode::Template {
+            return Err(ConfigCliError::InvalidDeployMode(format!(
+                "{} is a template, edit {} instead",
+                name,
+                configs[0].config.conf_location.display()
+            )));
+        }
+    }
+
+    let mut selection = match interactive {
+        true => Selection::Interactive,
+        false => Selection::All,
+    };
+    let mut synced = vec![];
+    for effective in configs {
+        let config = &effective.config;
+        let deployed_copy = std::fs::symlink_metadata(&config.symlink)
+            .map(|x| !x.file_type().is_symlink())
+            .unwrap_or(false);
+        if config.deploy_mode() == DeployMode::Template || !deployed_copy {
+            continue;
+        }
+
+        let filter = config.entry_filter()?;
+        for relative in changed_files(&config.symlink, &config.conf_location, filter.as_ref())? {
+            let live_path = join_relative(&config.symlink, &relative);
+            let store_path = join_relative(&config.conf_location, &relative);
+
+            let stored = match (store_path.exists(), config.secret) {
+                (false, _) => vec![],
+                (true, true) => decrypt_file(&store_path)?,
+                (true, false) => try_read_file!(&store_path),
+            };
+            let live = match live_path.exists() {
+                true => Some(try_read_file!(&live_path)),
+                false => None,
+            };
+            let live_content = live.clone().unwrap_or_default();
+            if stored == live_content && live.is_some() == store_path.exists() {
+                continue;
+            }
+
+            let masked = config.secret && !show_secrets;
+            let file_hunks = hunks(&stored, &live_content);
+            let shown_hunks: Vec<String> = match masked {
+                true => file_hunks.iter().map(|x| mask_diff(x)).collect(),
+                false => file_hunks.clone(),
+            };
+            let accepted = choose_hunks(&store_path, &shown_hunks, &mut selection)?;
+            if !accepted.iter().any(|x| *x) && !file_hunks.is_empty() {
+                continue;
+            }
+
+            // A file that was deleted from a deployed directory goes away once all of it is accepted
+            let remove = live.is_none() && accepted.iter().all(|x| *x);
+            let result = apply_hunks(&stored, &live_content, &accepted);
+            if !dry_run {
+                write_store_file(&effective, &store_path, &result, remove)?;
+            }
+
+            let label = store_path.to_string_lossy();
+            let diff = unified_diff(&stored, &result, &label, &live_path.to_string_lossy());
+            synced.push(SyncedFile {
+                config: config.name.clone(),
+                diff: match masked {
+                    true => mask_diff(&diff),
+                    false => diff,
+                },
+                path: store_path,
+            });
+        }
+    }
+    Ok(synced)
+}
+
+// Relative paths of every tracked file in either the live or the stored directory, or the empty
+// path for files
+fn changed_files(
+    live: &Path,
+    store: &Path,
+    filter: Option<&EntryFilter>,
+) -> ConfigResult<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    if !live.is_dir() || !store.is_dir() {
+        files.insert(PathBuf::new());
+        return Ok(files);
+    }
+    for root in [live, store] {
+        collect_files(root, Path::new(""), &mut files, filter)?;
+    }
+    Ok(files)
+}
+
+fn choose_hunks(
+    path: &Path,
+    file_hunks: &[String],
+    selection: &mut Selection,
+) -> ConfigResult<Vec<bool>> {
+    let mut accepted = vec![];
+    for hunk in file_hunks {
+        let accept = match selection {
+            Selection::All => true,
+            Selection::Quit => false,
+            Selection::Interactive => {
+                eprint!("{}\n{}", path.display(), colorize(hunk));
+                match prompt()? {
+                    'y' => true,
+                    'a' => {
+                        *selection = Selection::All;
+                        true
+                    }
+                    'q' => {
+                        *selection = Selection::Quit;
+                        false
+                    }
+                    _ => false,
+                }
+            }
+        };
+        accepted.push(accept);
+    }
+    Ok(accepted)
+}
+
+fn prompt() -> ConfigResult<char> {
+    loop {
+        eprint!("Sync this hunk into the theme [y,n,a,q]? ");
+        let _ = std::io::stderr().flush();
+
+        let mut answer = String::new();
+        if let Err(err) = std::io::stdin().read_line(&mut answer) {
+            return Err(ConfigCliError::FsReadError(err));
+        }
+        match answer.trim().chars().next() {
+            Some(answer) if "ynaq".contains(answer) => return Ok(answer),
+            // End of input counts as quitting
+            None if answer.is_empty() => return Ok('q'),
+            _ => eprintln!("y - sync, n - skip, a - sync this and everything after it, q - skip everything left"),
+        }
+    }
+}
+
+fn write_store_file(
+    config: &EffectiveConfig,
+    path: &Path,
+    content: &[u8],
+    remove: bool,
+) -> ConfigResult<()> {
+    if remove {
+        try_delete!(path);
+        return Ok(());
+    }
+
+    let content = match config.config.secret {
+        true => encrypt(&config.layer, content)?,
+        false => content.to_vec(),
+    };
+    if let Some(parent) = path.parent() {
+        if let Err(err) = std::fs::create_dir_all(parent) {
+            return Err(ConfigCliError::FileCreationError(err).at(parent));
+        }
+    }
+    match std::fs::write(path, content) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ConfigCliError::FsWriteError(err).at(path)),
+    }
+}
@@ -1,74 +1,33 @@
 use serde::{Deserialize, Serialize};
-use std::process::Command;
-
-use crate::error::ConfigCliError;
-use crate::utils::ConfigResult;
 
 pub mod add;
+pub mod install;
 pub mod list;
 pub mod remove;
 
 pub use add::*;
+pub use install::*;
 pub use list::*;
 pub use remove::*;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-pub struct Dependency(String);
-
-impl Dependency {
-    pub fn install(&self) -> ConfigResult<()> {
-        let output = match Command::new("yay").arg("-Q").arg(self.0.clone()).output() {
-            Ok(cli) => cli,
-            Err(err) => return Err(ConfigCliError::ShellInitError(err)),
-        };
-        if output.status.code() == Some(0) {
-            return Ok(());
-        }
-        let install_output = match Command::new("yay").arg("-S").arg(self.0.clone()).output() {
-            Ok(cli) => cli,
-            Err(err) => return Err(ConfigCliError::ShellInitError(err)),
-        };
-
-        match install_output.status.code() {
-            Some(0) => Ok(()),
-            _ => Err(ConfigCliError::GitCommandError(
-                install_output.status.to_string(),
-            )),
-        }
-    }
-}
-
-impl TryFrom<String> for Dependency {
-    type Error = ConfigCliError;
-    fn try_from(value: String) -> ConfigResult<Self> {
-        let output = match Command::new("yay").arg("-Ssq").arg(value.clone()).output() {
-            Ok(cli) => cli.stdout,
-            Err(err) => return Err(ConfigCliError::ShellInitError(err)),
-        };
-
-        let results: Vec<String> = match std::str::from_utf8(&output) {
-            Ok(s) => s.to_owned().lines().map(|x| x.to_owned()).collect(),
-            Err(err) => return Err(ConfigCliError::StringConversionError(err)),
-        };
-
-        if results.contains(&value) {
-            Ok(Dependency(value))
-        } else {
-            Err(ConfigCliError::NoPackageWithName(value))
-        }
-    }
-}
+pub struct Dependency(pub String);
 
-impl Into<Dependency> for (String, Dependency) {
-    fn into(self) -> Dependency {
-        self.1
+impl From<(String, Dependency)> for Dependency {
+    fn from(value: (String, Dependency)) -> Self {
+        value.1
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct DependencyFile {
+    #[serde(default)]
     pub globals: Vec<Dependency>,
+    #[serde(default)]
     pub config_bounds: Vec<(String, Dependency)>,
+    /// Dependencies only installed for a device or a device group
+    #[serde(default)]
+    pub device_bounds: Vec<(String, Dependency)>,
 }
 
 struct DependencyWrapper(String, Dependency);
@@ -79,20 +38,20 @@ impl FromIterator<DependencyWrapper> for Vec<Dependency> {
     }
 }
 
-impl Into<Dependency> for DependencyWrapper {
-    fn into(self) -> Dependency {
-        self.1
+impl From<DependencyWrapper> for Dependency {
+    fn from(value: DependencyWrapper) -> Self {
+        value.1
     }
 }
 
-impl Into<DependencyWrapper> for &(String, Dependency) {
-    fn into(self) -> DependencyWrapper {
-        DependencyWrapper(self.0.clone(), self.1.clone())
+impl From<&(String, Dependency)> for DependencyWrapper {
+    fn from(value: &(String, Dependency)) -> Self {
+        DependencyWrapper(value.0.clone(), value.1.clone())
     }
 }
 
-impl Into<DependencyWrapper> for (String, Dependency) {
-    fn into(self) -> DependencyWrapper {
-        DependencyWrapper(self.0, self.1)
+impl From<(String, Dependency)> for DependencyWrapper {
+    fn from(value: (String, Dependency)) -> Self {
+        DependencyWrapper(value.0, value.1)
     }
 }
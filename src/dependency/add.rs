@@ -1,36 +1,33 @@
-use super::{Dependency, DependencyFile, DependencyWrapper};
+use super::{Dependency, DependencyWrapper};
 use crate::error::ConfigCliError;
-use crate::utils::{get_base_dir, ConfigResult};
-use crate::{try_read_and_parse, try_write_file};
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::ConfigResult;
 
 pub fn add_dependency(
     theme: String,
     config: Option<String>,
+    device: Option<String>,
     dependency: String,
 ) -> ConfigResult<()> {
-    let path = get_base_dir()? + &theme + "/dependencies.toml";
-    let mut file_contents = try_read_and_parse!(path.clone(), DependencyFile);
+    let mut manifest = load_manifest(&theme)?;
+    let file_contents = &mut manifest.dependencies;
     let dependencies = &mut file_contents.globals;
     let config_dependencies: Vec<DependencyWrapper> = file_contents
         .config_bounds
         .iter()
-        .map(|x| Into::<DependencyWrapper>::into(x))
+        .map(Into::<DependencyWrapper>::into)
         .collect();
 
     let dependency = Dependency(dependency);
 
-    if config.is_some()
-        && config_dependencies
-            .iter()
-            .map(|x| x.0.clone())
-            .collect::<Vec<String>>()
-            .as_slice()
-            .contains(config.as_ref().unwrap())
+    if config
+        .as_ref()
+        .is_some_and(|config| config_dependencies.iter().any(|x| &x.0 == config))
     {
         dependencies.extend(
             config_dependencies
                 .into_iter()
-                .map(|x| Into::<Dependency>::into(x))
+                .map(Into::<Dependency>::into)
                 .collect::<Vec<Dependency>>(),
         );
     }
@@ -39,14 +36,21 @@ pub fn add_dependency(
         return Err(ConfigCliError::InvalidDependencyName(dependency.0));
     }
 
-    if config.is_some() {
+    if device.as_ref().is_some_and(|device| {
         file_contents
-            .config_bounds
-            .push((config.unwrap(), dependency));
-    } else {
-        file_contents.globals.push(dependency);
+            .device_bounds
+            .iter()
+            .any(|x| &x.0 == device && x.1 == dependency)
+    }) {
+        return Err(ConfigCliError::InvalidDependencyName(dependency.0));
+    }
+
+    match (config, device) {
+        (Some(config), _) => file_contents.config_bounds.push((config, dependency)),
+        (None, Some(device)) => file_contents.device_bounds.push((device, dependency)),
+        (None, None) => file_contents.globals.push(dependency),
     }
 
-    try_write_file!(path, &file_contents);
+    save_manifest(&theme, &manifest)?;
     Ok(())
 }
@@ -0,0 +1,125 @@
+use colored::Colorize;
+use serde::Serialize;
+
+use super::Dependency;
+use crate::device::device_lineage;
+use crate::package_manager::{get_package_manager, PackageManager};
+use crate::theme::{applied_configs, load_manifest, theme_chain};
+use crate::utils::ConfigResult;
+
+/// All dependencies of a theme, split into the ones that are installed and the ones that are missing
+#[derive(Debug, Default, Serialize)]
+pub struct InstallPlan {
+    pub installed: Vec<Dependency>,
+    pub missing: Vec<Dependency>,
+}
+
+impl InstallPlan {
+    /// Queries which of the dependencies are missing
+    pub fn from_dependencies(
+        dependencies: Vec<Dependency>,
+        package_manager: &dyn PackageManager,
+    ) -> ConfigResult<Self> {
+        let names: Vec<String> = dependencies.iter().map(|x| x.0.clone()).collect();
+
+        let installed_names = match names.is_empty() {
+            true => vec![],
+            false => package_manager.query(&names)?,
+        };
+
+        let (installed, missing) = dependencies
+            .into_iter()
+            .partition(|x| installed_names.contains(&x.0));
+
+        Ok(InstallPlan { installed, missing })
+    }
+
+    /// Installs every missing dependency in a single package manager transaction
+    pub fn execute(self, package_manager: &dyn PackageManager) -> ConfigResult<Installation> {
+        if !self.missing.is_empty() {
+            let names: Vec<String> = self.missing.iter().map(|x| x.0.clone()).collect();
+            // Shown before the package manager asks to confirm, on stderr so JSON output stays intact
+            eprintln!(
+                "{} {} {}",
+                "Installing".bold(),
+                names.len(),
+                format!("package(s) with {}:", package_manager.name()).bold()
+            );
+            for name in &names {
+                eprintln!("  {}", name.yellow());
+            }
+            package_manager.install(&names)?;
+        }
+        Ok(Installation {
+            package_manager: package_manager.name().to_owned(),
+            plan: self,
+        })
+    }
+}
+
+/// An executed install plan, `missing` holds what was installed
+#[derive(Debug, Serialize)]
+pub struct Installation {
+    pub package_manager: String,
+    #[serde(flatten)]
+    pub plan: InstallPlan,
+}
+
+/// Plans and installs all dependencies of the theme for the device, nothing if it has none
+pub fn install_dependencies(
+    theme: &str,
+    device: Option<&str>,
+) -> ConfigResult<Option<Installation>> {
+    let dependencies = collect_dependencies(theme, device)?;
+
+    // A package manager is only needed if the theme has dependencies at all
+    if dependencies.is_empty() {
+        return Ok(None);
+    }
+
+    let package_manager = get_package_manager(theme, device)?;
+    let plan = InstallPlan::from_dependencies(dependencies, package_manager.as_ref())?;
+    plan.execute(package_manager.as_ref()).map(Some)
+}
+
+/// Global dependencies, dependencies bound to a config applied on this machine or to a name of the device lineage
+/// and the dependencies of the configs
+///
+/// Dependencies of every theme in the inheritance chain are included.
+pub fn collect_dependencies(theme: &str, device: Option<&str>) -> ConfigResult<Vec<Dependency>> {
+    let (configs, _) = applied_configs(theme, device)?;
+    let lineage = match device {
+        Some(device) => device_lineage(theme, device)?,
+        None => vec![],
+    };
+
+    let mut dependencies: Vec<Dependency> = vec![];
+    for layer in theme_chain(theme)? {
+        let dependency_file = load_manifest(&layer)?.dependencies;
+
+        dependencies.extend(dependency_file.globals);
+        dependencies.extend(
+            dependency_file
+                .config_bounds
+                .into_iter()
+                .filter(|x| configs.iter().any(|conf| conf.name == x.0))
+                .map(Into::<Dependency>::into),
+        );
+        dependencies.extend(
+            dependency_file
+                .device_bounds
+                .into_iter()
+                .filter(|x| lineage.contains(&x.0))
+                .map(Into::<Dependency>::into),
+        );
+    }
+    dependencies.extend(configs.into_iter().flat_map(|x| x.dependencies));
+
+    let mut unique: Vec<Dependency> = vec![];
+    for dependency in dependencies {
+        if !unique.contains(&dependency) {
+            unique.push(dependency);
+        }
+    }
+    Ok(unique)
+}
@@ -1,25 +1,33 @@
-use crate::config::{Config, ConfigFile};
-use crate::error::ConfigCliError;
-use crate::try_read_and_parse;
-use crate::utils::{get_base_dir, ConfigResult};
+use crate::theme::{layered_configs, load_manifest, theme_chain};
+use crate::utils::ConfigResult;
 
+/// Dependencies bound to the config with `dependency add` and those listed in the config itself
+///
+/// Dependencies of every theme in the inheritance chain are included.
 pub fn list_dependencies(theme: String, config_name: String) -> ConfigResult<Vec<String>> {
-    let theme = get_base_dir()? + &theme + "/configs.toml";
-
-    let config_file = try_read_and_parse!(theme, ConfigFile);
-
-    let mut all_deps: Vec<Config> = config_file.globals;
-    all_deps.extend(
-        config_file
-            .device_bounds
+    let mut dependencies: Vec<String> = vec![];
+    for layer in theme_chain(&theme)? {
+        dependencies.extend(
+            load_manifest(&layer)?
+                .dependencies
+                .config_bounds
+                .into_iter()
+                .filter(|x| x.0 == config_name)
+                .map(|x| x.1 .0),
+        );
+    }
+    dependencies.extend(
+        layered_configs(&theme)?
             .into_iter()
-            .map(|x| x.1)
-            .collect::<Vec<Config>>(),
+            .filter(|x| x.config.name == config_name)
+            .flat_map(|x| x.config.dependencies.into_iter().map(|x| x.0)),
     );
 
-    Ok(all_deps
-        .into_iter()
-        .filter(|x| x.name == config_name)
-        .map(|x| x.dependencies.into_iter().map(|x| x.0).collect::<String>())
-        .collect())
+    let mut unique: Vec<String> = vec![];
+    for dependency in dependencies {
+        if !unique.contains(&dependency) {
+            unique.push(dependency);
+        }
+    }
+    Ok(unique)
 }
@@ -1,17 +1,18 @@
 use super::{Dependency, DependencyFile, DependencyWrapper};
 use crate::error::ConfigCliError;
-use crate::utils::{get_base_dir, ConfigResult};
-use crate::{try_read_and_parse, try_write_file};
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::ConfigResult;
 
 pub fn remove_dependency(theme: String, dependency: String) -> ConfigResult<()> {
-    let path = get_base_dir()? + &theme + "/dependencies.toml";
-    let file_contents = try_read_and_parse!(path.clone(), DependencyFile);
+    let mut manifest = load_manifest(&theme)?;
+    let file_contents = manifest.dependencies.clone();
 
     let mut all_dependencies = file_contents.globals.clone();
     let config_dependencies: Vec<Dependency> = file_contents
         .config_bounds
         .iter()
-        .map(|x| Into::<DependencyWrapper>::into(x))
+        .chain(&file_contents.device_bounds)
+        .map(Into::<DependencyWrapper>::into)
         .collect();
     all_dependencies.extend(config_dependencies.clone());
 
@@ -24,15 +25,22 @@ pub fn remove_dependency(theme: String, dependency: String) -> ConfigResult<()>
                 .into_iter()
                 .filter(|x| x.1 .0 != dependency.0)
                 .collect(),
+            device_bounds: file_contents
+                .device_bounds
+                .into_iter()
+                .filter(|x| x.1 .0 != dependency.0)
+                .collect(),
             globals: file_contents.globals,
         };
 
-        try_write_file!(path, &new_file_contents);
+        manifest.dependencies = new_file_contents;
+        save_manifest(&theme, &manifest)?;
 
         Ok(())
     } else if all_dependencies.contains(&dependency) {
         let new_file_contents: DependencyFile = DependencyFile {
             config_bounds: file_contents.config_bounds,
+            device_bounds: file_contents.device_bounds,
             globals: file_contents
                 .globals
                 .into_iter()
@@ -40,7 +48,8 @@ pub fn remove_dependency(theme: String, dependency: String) -> ConfigResult<()>
                 .collect(),
         };
 
-        try_write_file!(path, &new_file_contents);
+        manifest.dependencies = new_file_contents;
+        save_manifest(&theme, &manifest)?;
 
         Ok(())
     } else {
@@ -1,7 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::package_manager::PackageManagerKind;
+
+pub mod add;
+pub mod detect;
+pub mod lineage;
 pub mod list;
 pub mod remove;
+pub mod rename;
+pub mod set_group;
+pub mod set_match;
+pub mod set_package_manager;
+pub mod set_parent;
+pub mod show;
 pub mod use_device;
 
+pub use add::*;
+pub use detect::*;
+pub use lineage::*;
 pub use list::*;
 pub use remove::*;
+pub use rename::*;
+pub use set_group::*;
+pub use set_match::*;
+pub use set_package_manager::*;
+pub use set_parent::*;
+pub use show::*;
 pub use use_device::*;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct DeviceFile {
+    /// Devices added explicitly, a device that is only bound to is known all the same
+    #[serde(default)]
+    pub registry: Vec<String>,
+    #[serde(default)]
+    pub package_managers: Vec<(String, PackageManagerKind)>,
+    /// Rules that pick the device when none is given
+    #[serde(default)]
+    pub matches: Vec<(String, DeviceMatch)>,
+    /// The device each device inherits from
+    #[serde(default)]
+    pub parents: Vec<(String, String)>,
+    /// Named sets of devices, a group can be bound to like a single device
+    #[serde(default)]
+    pub groups: Vec<(String, Vec<String>)>,
+}
+
+/// Identifies the machine of a device, every rule that is set has to hold
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct DeviceMatch {
+    /// Glob like `work-*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine_id: Option<String>,
+    /// Glob over the `ID` of os-release, like `arch` or `debian`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+    /// Glob over the `VERSION_ID` of os-release
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery: Option<bool>,
+}
+
+impl DeviceMatch {
+    pub fn is_empty(&self) -> bool {
+        self.hostname.is_none()
+            && self.machine_id.is_none()
+            && self.os.is_none()
+            && self.os_version.is_none()
+            && self.arch.is_none()
+            && self.battery.is_none()
+    }
+}
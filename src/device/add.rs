@@ -0,0 +1,23 @@
+use super::{known_devices, DeviceGraph};
+use crate::error::ConfigCliError;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::{get_current_theme, ConfigResult};
+
+/// Registers the device in the theme, so it is known before anything is bound to it
+pub fn add_device(name: String) -> ConfigResult<()> {
+    let theme = get_current_theme()?;
+    check_device_name(&theme, &name)?;
+
+    let mut manifest = load_manifest(&theme)?;
+    manifest.devices.registry.push(name);
+    save_manifest(&theme, &manifest)
+}
+
+/// Fails if the name is already taken by a device or a group
+pub fn check_device_name(theme: &str, name: &str) -> ConfigResult<()> {
+    let is_group = DeviceGraph::load(theme)?.groups.iter().any(|x| x.0 == name);
+    if is_group || known_devices(theme)?.iter().any(|x| x == name) {
+        return Err(ConfigCliError::DeviceAlreadyExists(name.to_owned()));
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use serde::Serialize;
+
+use super::DeviceMatch;
+use crate::facts::{arch, has_battery, hostname, machine_id, os, os_version};
+use crate::theme::{load_manifest, theme_chain};
+use crate::utils::{get_current_theme, glob_matches, ConfigResult};
+
+/// One rule of a device profile compared against this machine
+#[derive(Debug, Serialize)]
+pub struct RuleCheck {
+    pub rule: &'static str,
+    pub expected: String,
+    pub actual: String,
+    pub matched: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileCheck {
+    pub device: String,
+    /// The theme of the inheritance chain declaring the rules
+    pub theme: String,
+    pub checks: Vec<RuleCheck>,
+    /// Every rule holds, a profile without rules never matches
+    pub matched: bool,
+}
+
+/// Which device this machine is taken for and why
+#[derive(Debug, Serialize)]
+pub struct DeviceDetection {
+    pub theme: String,
+    pub device: Option<String>,
+    pub profiles: Vec<ProfileCheck>,
+}
+
+// Read once, every profile is checked against the same facts
+struct MachineFacts {
+    hostname: String,
+    machine_id: String,
+    os: String,
+    os_version: String,
+    arch: String,
+    battery: bool,
+}
+
+impl MachineFacts {
+    fn read() -> Self {
+        MachineFacts {
+            hostname: hostname(),
+            machine_id: machine_id(),
+            os: os(),
+            os_version: os_version(),
+            arch: arch(),
+            battery: has_battery(),
+        }
+    }
+}
+
+fn check_rules(rules: &DeviceMatch, facts: &MachineFacts) -> ConfigResult<Vec<RuleCheck>> {
+    let mut checks = vec![];
+
+    let globs = [
+        ("hostname", &rules.hostname, &facts.hostname),
+        ("os", &rules.os, &facts.os),
+        ("os_version", &rules.os_version, &facts.os_version),
+        ("arch", &rules.arch, &facts.arch),
+    ];
+    for (rule, expected, actual) in globs {
+        if let Some(expected) = expected {
+            checks.push(RuleCheck {
+                rule,
+                expected: expected.clone(),
+                actual: actual.clone(),
+                matched: glob_matches(expected, actual)?,
+            });
+        }
+    }
+
+    if let Some(expected) = &rules.machine_id {
+        checks.push(RuleCheck {
+            rule: "machine_id",
+            expected: expected.clone(),
+            actual: facts.machine_id.clone(),
+            matched: *expected == facts.machine_id,
+        });
+    }
+    if let Some(expected) = rules.battery {
+        checks.push(RuleCheck {
+            rule: "battery",
+            expected: expected.to_string(),
+            actual: facts.battery.to_string(),
+            matched: expected == facts.battery,
+        });
+    }
+    Ok(checks)
+}
+
+/// Checks the device profiles of the theme and its parents against this machine
+///
+/// The matching profile with the most rules wins, ties go to the one declared first, starting
+/// at the theme itself.
+pub fn detect_device(theme: &str) -> ConfigResult<DeviceDetection> {
+    let facts = MachineFacts::read();
+
+    let mut profiles: Vec<ProfileCheck> = vec![];
+    for layer in theme_chain(theme)?.iter().rev() {
+        for (device, rules) in load_manifest(layer)?.devices.matches {
+            // A theme overrides the rules its parents declare for the same device
+            if profiles.iter().any(|x| x.device == device) {
+                continue;
+            }
+            let checks = check_rules(&rules, &facts)?;
+            profiles.push(ProfileCheck {
+                matched: !checks.is_empty() && checks.iter().all(|x| x.matched),
+                checks,
+                device,
+                theme: layer.clone(),
+            });
+        }
+    }
+
+    let device = profiles
+        .iter()
+        .filter(|x| x.matched)
+        .rev()
+        .max_by_key(|x| x.checks.len())
+        .map(|x| x.device.clone());
+
+    Ok(DeviceDetection {
+        theme: theme.to_owned(),
+        device,
+        profiles,
+    })
+}
+
+/// The device given, or the one detected for the theme if there is none
+pub fn resolve_device(theme: &str, device: Option<String>) -> ConfigResult<Option<String>> {
+    match device {
+        Some(device) => Ok(Some(device)),
+        None => Ok(detect_device(theme)?.device),
+    }
+}
+
+pub fn current_device() -> ConfigResult<DeviceDetection> {
+    detect_device(&get_current_theme()?)
+}
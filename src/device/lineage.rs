@@ -0,0 +1,81 @@
+use crate::error::ConfigCliError;
+use crate::theme::{load_manifest, theme_chain};
+use crate::utils::ConfigResult;
+
+/// Parents and groups of the inheritance chain, where a theme overrides what its parents declare
+/// for the same device or group
+pub struct DeviceGraph {
+    pub parents: Vec<(String, String)>,
+    pub groups: Vec<(String, Vec<String>)>,
+}
+
+impl DeviceGraph {
+    pub fn load(theme: &str) -> ConfigResult<Self> {
+        let mut graph = DeviceGraph {
+            parents: vec![],
+            groups: vec![],
+        };
+
+        for layer in theme_chain(theme)?.iter().rev() {
+            let device_file = load_manifest(layer)?.devices;
+            for parent in device_file.parents {
+                if !graph.parents.iter().any(|x| x.0 == parent.0) {
+                    graph.parents.push(parent);
+                }
+            }
+            for group in device_file.groups {
+                if !graph.groups.iter().any(|x| x.0 == group.0) {
+                    graph.groups.push(group);
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    pub fn parent(&self, device: &str) -> Option<&String> {
+        self.parents.iter().find(|x| x.0 == device).map(|x| &x.1)
+    }
+
+    /// The device followed by the devices it inherits from, closest first
+    pub fn ancestry(&self, device: &str) -> ConfigResult<Vec<String>> {
+        let mut ancestry = vec![device.to_owned()];
+        let mut current = device;
+        while let Some(parent) = self.parent(current) {
+            let cycle = ancestry.contains(parent);
+            ancestry.push(parent.clone());
+            if cycle {
+                return Err(ConfigCliError::DeviceCycle(ancestry.join(" -> ")));
+            }
+            current = parent;
+        }
+        Ok(ancestry)
+    }
+
+    /// The names anything bound to applies to the device through, least specific first
+    ///
+    /// Every ancestor, starting at the most distant one, contributes its groups in the order
+    /// they are declared and then itself. A name that turns up twice keeps its later place.
+    pub fn lineage(&self, device: &str) -> ConfigResult<Vec<String>> {
+        let mut lineage: Vec<String> = vec![];
+        for name in self.ancestry(device)?.iter().rev() {
+            let groups = self
+                .groups
+                .iter()
+                .filter(|x| x.1.contains(name))
+                .map(|x| &x.0);
+            for name in groups.chain([name]) {
+                lineage.retain(|x| x != name);
+                lineage.push(name.clone());
+            }
+        }
+        Ok(lineage)
+    }
+}
+
+/// The order configs, dependencies and variables bound to a device or group are resolved in
+///
+/// Theme wide values come first, then the values of every name of the lineage, each replacing
+/// what came before. Within a name, a theme replaces the values its parents bind to it.
+pub fn device_lineage(theme: &str, device: &str) -> ConfigResult<Vec<String>> {
+    DeviceGraph::load(theme)?.lineage(device)
+}
@@ -1,18 +1,40 @@
-use itertools::Itertools;
+use std::collections::BTreeSet;
 
-use crate::config::ConfigFile;
-use crate::error::ConfigCliError;
-use crate::try_read_and_parse;
-use crate::utils::{get_base_dir, get_current_theme, ConfigResult};
+use crate::theme::{load_manifest, theme_chain};
+use crate::utils::{get_current_theme, ConfigResult};
 
 pub fn list_devices() -> ConfigResult<Vec<String>> {
-    let config_file_location = get_base_dir()? + &get_current_theme()? + "/configs.toml";
-    Ok(
-        try_read_and_parse!(config_file_location.clone(), ConfigFile)
-            .device_bounds
-            .into_iter()
-            .map(|x| x.0)
-            .dedup()
-            .collect(),
-    )
+    known_devices(&get_current_theme()?)
+}
+
+/// Every device of the theme and its parents, the registered ones and the ones only bound to
+///
+/// Group names are not devices, even though things are bound to them the same way.
+pub fn known_devices(theme: &str) -> ConfigResult<Vec<String>> {
+    let mut names: BTreeSet<String> = BTreeSet::new();
+    let mut groups: Vec<String> = vec![];
+
+    for layer in theme_chain(theme)? {
+        let manifest = load_manifest(&layer)?;
+        let device_file = manifest.devices;
+
+        names.extend(device_file.registry);
+        names.extend(device_file.package_managers.into_iter().map(|x| x.0));
+        names.extend(device_file.matches.into_iter().map(|x| x.0));
+        for (device, parent) in device_file.parents {
+            names.insert(device);
+            names.insert(parent);
+        }
+        for (group, devices) in device_file.groups {
+            groups.push(group);
+            names.extend(devices);
+        }
+
+        names.extend(manifest.configs.device_bounds.into_iter().map(|x| x.0));
+        names.extend(manifest.dependencies.device_bounds.into_iter().map(|x| x.0));
+        names.extend(manifest.variables.device_bounds.into_iter().map(|x| x.0));
+        names.extend(manifest.variables.device_secrets.into_iter().map(|x| x.0));
+    }
+
+    Ok(names.into_iter().filter(|x| !groups.contains(x)).collect())
 }
@@ -1,18 +1,72 @@
-use crate::config::ConfigFile;
+use super::{known_devices, DeviceGraph};
+use crate::backup::Backup;
+use crate::config::Config;
 use crate::error::ConfigCliError;
-use crate::utils::{get_base_dir, get_current_theme, ConfigResult};
-use crate::{try_read_and_parse, try_write_file};
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::{get_current_theme, ConfigResult};
+use crate::{try_delete, try_delete_recursive};
 
-pub fn remove_device(name: String) -> ConfigResult<()> {
-    let config_file_location = get_base_dir()? + &get_current_theme()? + "/configs.toml";
-    let mut config_file = try_read_and_parse!(config_file_location.clone(), ConfigFile);
-    config_file.device_bounds = config_file
+/// Removes the device or group and everything bound to it from the theme
+///
+/// Configs bound to it would be left without anything applying them, so the removal is refused
+/// unless forced. Forcing removes them as well, leaving their targets standalone and deleting
+/// their stored files, and returns them.
+pub fn remove_device(name: String, force: bool) -> ConfigResult<Vec<Config>> {
+    let theme = get_current_theme()?;
+    let is_group = DeviceGraph::load(&theme)?
+        .groups
+        .iter()
+        .any(|x| x.0 == name);
+    if !is_group && !known_devices(&theme)?.contains(&name) {
+        return Err(ConfigCliError::UnknownDevice(name));
+    }
+
+    let mut manifest = load_manifest(&theme)?;
+    let (orphaned, kept): (Vec<(String, Config)>, _) = manifest
+        .configs
         .device_bounds
         .into_iter()
-        .filter(|x| x.0 != name)
-        .collect();
+        .partition(|x| x.0 == name);
+    let orphaned: Vec<Config> = orphaned.into_iter().map(|x| x.1).collect();
+    if !orphaned.is_empty() && !force {
+        let names: Vec<&str> = orphaned.iter().map(|x| x.name.as_str()).collect();
+        return Err(ConfigCliError::StillInUse(format!(
+            "configs bound to {}: {}",
+            name,
+            names.join(", ")
+        )));
+    }
+    manifest.configs.device_bounds = kept;
+    manifest.dependencies.device_bounds.retain(|x| x.0 != name);
+    manifest.variables.device_bounds.retain(|x| x.0 != name);
+    manifest.variables.device_secrets.retain(|x| x.0 != name);
+
+    let device_file = &mut manifest.devices;
+    device_file.registry.retain(|x| *x != name);
+    device_file.package_managers.retain(|x| x.0 != name);
+    device_file.matches.retain(|x| x.0 != name);
+    device_file.parents.retain(|x| x.0 != name && x.1 != name);
+    // The name is either a group or a device that is a member of groups
+    device_file.groups.retain(|x| x.0 != name);
+    for group in device_file.groups.iter_mut() {
+        group.1.retain(|x| *x != name);
+    }
+    device_file.groups.retain(|x| !x.1.is_empty());
 
-    try_write_file!(&config_file_location, &config_file);
+    let mut backup = Backup::begin(&format!("device remove {}", name))?;
+    for config in &orphaned {
+        config.detach(&mut backup)?;
+        if !config.conf_location.exists() {
+            continue;
+        }
+        backup.snapshot(&config.conf_location)?;
+        match config.conf_location.is_dir() {
+            true => try_delete_recursive!(&config.conf_location),
+            false => try_delete!(&config.conf_location),
+        }
+    }
+    backup.finish()?;
 
-    Ok(())
+    save_manifest(&theme, &manifest)?;
+    Ok(orphaned)
 }
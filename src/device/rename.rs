@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use super::{check_device_name, known_devices};
+use crate::error::ConfigCliError;
+use crate::theme::{list_themes, load_manifest, save_manifest, ThemeManifest};
+use crate::utils::{get_current_theme, ConfigResult};
+
+/// Renames the device in every binding of every theme in the store
+///
+/// The new name is checked against every theme the device is known in, including through a parent
+/// that gets rewritten, before anything is saved, so bindings of two devices are never merged.
+pub fn rename_device(name: String, new_name: String) -> ConfigResult<Vec<String>> {
+    let theme = get_current_theme()?;
+    if !known_devices(&theme)?.contains(&name) {
+        return Err(ConfigCliError::UnknownDevice(name));
+    }
+
+    let mut renamed = vec![];
+    for path in list_themes()? {
+        let theme = match Path::new(&path).file_name() {
+            Some(theme) => theme.to_string_lossy().to_string(),
+            None => continue,
+        };
+        if known_devices(&theme)?.contains(&name) {
+            check_device_name(&theme, &new_name).map_err(|err| err.context(&theme))?;
+        }
+        let mut manifest = load_manifest(&theme)?;
+        if rename_bindings(&mut manifest, &name, &new_name) {
+            renamed.push((theme, manifest));
+        }
+    }
+
+    for (theme, manifest) in &renamed {
+        save_manifest(theme, manifest)?;
+    }
+    Ok(renamed.into_iter().map(|x| x.0).collect())
+}
+
+fn rename_bindings(manifest: &mut ThemeManifest, name: &str, new_name: &str) -> bool {
+    let device_file = &mut manifest.devices;
+    let keys = device_file
+        .registry
+        .iter_mut()
+        .chain(device_file.package_managers.iter_mut().map(|x| &mut x.0))
+        .chain(device_file.matches.iter_mut().map(|x| &mut x.0))
+        .chain(
+            device_file
+                .parents
+                .iter_mut()
+                .flat_map(|x| [&mut x.0, &mut x.1]),
+        )
+        .chain(device_file.groups.iter_mut().flat_map(|x| x.1.iter_mut()))
+        .chain(manifest.configs.device_bounds.iter_mut().map(|x| &mut x.0))
+        .chain(
+            manifest
+                .dependencies
+                .device_bounds
+                .iter_mut()
+                .map(|x| &mut x.0),
+        )
+        .chain(
+            manifest
+                .variables
+                .device_bounds
+                .iter_mut()
+                .map(|x| &mut x.0),
+        )
+        .chain(
+            manifest
+                .variables
+                .device_secrets
+                .iter_mut()
+                .map(|x| &mut x.0),
+        );
+
+    let mut renamed = false;
+    for key in keys.filter(|x| *x == name) {
+        *key = new_name.to_owned();
+        renamed = true;
+    }
+    renamed
+}
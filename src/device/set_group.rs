@@ -0,0 +1,26 @@
+use crate::error::ConfigCliError;
+use crate::theme::{load_manifest, save_manifest, theme_chain};
+use crate::utils::{get_current_theme, ConfigResult};
+
+/// Replaces the devices of the group, no devices at all removes it
+pub fn set_device_group(name: String, devices: Vec<String>) -> ConfigResult<()> {
+    let theme = get_current_theme()?;
+    for layer in theme_chain(&theme)? {
+        if load_manifest(&layer)?.devices.registry.contains(&name) {
+            return Err(ConfigCliError::DeviceAlreadyExists(name));
+        }
+    }
+
+    let mut manifest = load_manifest(&theme)?;
+    manifest.devices.groups.retain(|x| x.0 != name);
+    if !devices.is_empty() {
+        let mut members: Vec<String> = vec![];
+        for device in devices {
+            if !members.contains(&device) {
+                members.push(device);
+            }
+        }
+        manifest.devices.groups.push((name, members));
+    }
+    save_manifest(&theme, &manifest)
+}
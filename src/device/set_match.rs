@@ -0,0 +1,19 @@
+use super::DeviceMatch;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::{get_current_theme, glob_matches, ConfigResult};
+
+/// Replaces the rules that detect the device, no rules at all removes them
+pub fn set_device_match(name: String, rules: DeviceMatch) -> ConfigResult<()> {
+    let globs = [&rules.hostname, &rules.os, &rules.os_version, &rules.arch];
+    for pattern in globs.into_iter().flatten() {
+        glob_matches(pattern, "")?;
+    }
+
+    let theme = get_current_theme()?;
+    let mut manifest = load_manifest(&theme)?;
+    manifest.devices.matches.retain(|x| x.0 != name);
+    if !rules.is_empty() {
+        manifest.devices.matches.push((name, rules));
+    }
+    save_manifest(&theme, &manifest)
+}
@@ -0,0 +1,16 @@
+use crate::package_manager::PackageManagerKind;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::{get_current_theme, ConfigResult};
+
+pub fn set_package_manager(name: String, package_manager: PackageManagerKind) -> ConfigResult<()> {
+    let theme = get_current_theme()?;
+    let mut manifest = load_manifest(&theme)?;
+    let device_file = &mut manifest.devices;
+
+    device_file.package_managers.retain(|x| x.0 != name);
+    device_file.package_managers.push((name, package_manager));
+
+    save_manifest(&theme, &manifest)?;
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use super::DeviceGraph;
+use crate::error::ConfigCliError;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::{get_current_theme, ConfigResult};
+
+/// Makes the device inherit from the parent, no parent removes the inheritance
+pub fn set_device_parent(name: String, parent: Option<String>) -> ConfigResult<()> {
+    let theme = get_current_theme()?;
+
+    if let Some(parent) = &parent {
+        let ancestry = DeviceGraph::load(&theme)?.ancestry(parent)?;
+        if ancestry.contains(&name) {
+            let mut cycle = vec![name.clone()];
+            cycle.extend(ancestry.into_iter().take_while(|x| *x != name));
+            cycle.push(name);
+            return Err(ConfigCliError::DeviceCycle(cycle.join(" -> ")));
+        }
+    }
+
+    let mut manifest = load_manifest(&theme)?;
+    manifest.devices.parents.retain(|x| x.0 != name);
+    if let Some(parent) = parent {
+        manifest.devices.parents.push((name, parent));
+    }
+    save_manifest(&theme, &manifest)
+}
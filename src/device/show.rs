@@ -0,0 +1,82 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::{detect_device, known_devices, DeviceGraph, RuleCheck};
+use crate::dependency::collect_dependencies;
+use crate::error::ConfigCliError;
+use crate::facts::builtin_facts;
+use crate::theme::{effective_configs, load_manifest, theme_chain, EffectiveConfig};
+use crate::utils::{get_current_theme, ConfigResult};
+use crate::variable::list_variables;
+
+/// Everything the current theme applies for a device
+#[derive(Debug, Serialize)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub theme: String,
+    /// Added with `device add` rather than only bound to
+    pub registered: bool,
+    pub parent: Option<String>,
+    pub groups: Vec<String>,
+    /// Groups and devices bindings are resolved through, least specific first
+    pub lineage: Vec<String>,
+    pub configs: Vec<EffectiveConfig>,
+    pub dependencies: Vec<String>,
+    /// Template variables the device sees apart from the built-in facts, secrets masked
+    pub variables: BTreeMap<String, String>,
+    /// The rules identifying the device, checked against this machine
+    pub facts: Vec<RuleCheck>,
+    /// This machine is detected as the device
+    pub detected: bool,
+}
+
+pub fn show_device(name: String) -> ConfigResult<DeviceInfo> {
+    let theme = get_current_theme()?;
+    if !known_devices(&theme)?.contains(&name) {
+        return Err(ConfigCliError::UnknownDevice(name));
+    }
+
+    let mut registered = false;
+    for layer in theme_chain(&theme)? {
+        registered |= load_manifest(&layer)?.devices.registry.contains(&name);
+    }
+
+    let graph = DeviceGraph::load(&theme)?;
+    let groups = graph
+        .groups
+        .iter()
+        .filter(|x| x.1.contains(&name))
+        .map(|x| x.0.clone())
+        .collect();
+
+    let facts = builtin_facts();
+    let variables = list_variables(theme.clone(), Some(name.clone()))?
+        .into_iter()
+        .filter(|x| x.0 != "theme" && x.0 != "device" && facts.get(&x.0) != Some(&x.1))
+        .collect();
+
+    let detection = detect_device(&theme)?;
+    let facts = detection
+        .profiles
+        .into_iter()
+        .find(|x| x.device == name)
+        .map(|x| x.checks)
+        .unwrap_or_default();
+
+    Ok(DeviceInfo {
+        registered,
+        parent: graph.parent(&name).cloned(),
+        groups,
+        lineage: graph.lineage(&name)?,
+        configs: effective_configs(&theme, Some(&name))?,
+        dependencies: collect_dependencies(&theme, Some(&name))?
+            .into_iter()
+            .map(|x| x.0)
+            .collect(),
+        variables,
+        facts,
+        detected: detection.device.as_ref() == Some(&name),
+        theme,
+        name,
+    })
+}
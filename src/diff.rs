@@ -0,0 +1,156 @@
+use colored::Colorize;
+use similar::{DiffOp, DiffTag, TextDiff};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::config::EntryFilter;
+use crate::error::ConfigCliError;
+use crate::utils::ConfigResult;
+
+pub mod compare;
+
+pub use compare::*;
+
+// Lines of unchanged context around every hunk
+const CONTEXT: usize = 3;
+
+fn as_text(content: &[u8]) -> Option<&str> {
+    std::str::from_utf8(content).ok()
+}
+
+/// Unified diff from old to new, empty if both are the same
+pub fn unified_diff(old: &[u8], new: &[u8], old_label: &str, new_label: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    match (as_text(old), as_text(new)) {
+        (Some(old), Some(new)) => TextDiff::from_lines(old, new)
+            .unified_diff()
+            .context_radius(CONTEXT)
+            .header(old_label, new_label)
+            .to_string(),
+        _ => format!("Binary files {} and {} differ\n", old_label, new_label),
+    }
+}
+
+/// Every hunk of the diff from old to new on its own, binary content is a single hunk
+pub fn hunks(old: &[u8], new: &[u8]) -> Vec<String> {
+    if old == new {
+        return vec![];
+    }
+    match (as_text(old), as_text(new)) {
+        (Some(old), Some(new)) => TextDiff::from_lines(old, new)
+            .unified_diff()
+            .context_radius(CONTEXT)
+            .iter_hunks()
+            .map(|x| x.to_string())
+            .collect(),
+        _ => vec!["Binary content differs\n".to_owned()],
+    }
+}
+
+/// Applies the accepted hunks of the diff from old to new onto old
+pub fn apply_hunks(old: &[u8], new: &[u8], accepted: &[bool]) -> Vec<u8> {
+    let (old_text, new_text) = match (as_text(old), as_text(new)) {
+        (Some(old), Some(new)) => (old, new),
+        _ => match accepted.first() {
+            Some(true) => return new.to_vec(),
+            _ => return old.to_vec(),
+        },
+    };
+
+    let diff = TextDiff::from_lines(old_text, new_text);
+    let accepted_ops: Vec<DiffOp> = diff
+        .grouped_ops(CONTEXT)
+        .into_iter()
+        .zip(accepted)
+        .filter(|x| *x.1)
+        .flat_map(|x| x.0)
+        .filter(|x| x.tag() != DiffTag::Equal)
+        .collect();
+
+    let mut result = String::new();
+    for op in diff.ops() {
+        let lines = match op.tag() != DiffTag::Equal && accepted_ops.contains(op) {
+            true => &diff.new_slices()[op.new_range()],
+            false => &diff.old_slices()[op.old_range()],
+        };
+        lines.iter().for_each(|x| result.push_str(x));
+    }
+    result.into_bytes()
+}
+
+/// Colors the added and removed lines of a unified diff
+pub fn colorize(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            let colored = if line.starts_with("+++") || line.starts_with("---") {
+                line.bold()
+            } else if line.starts_with('+') {
+                line.green()
+            } else if line.starts_with('-') {
+                line.red()
+            } else if line.starts_with("@@") {
+                line.cyan()
+            } else {
+                line.normal()
+            };
+            colored.to_string() + "\n"
+        })
+        .collect()
+}
+
+/// Hides the content of every line of a unified diff, only the headers and what changed stay
+pub fn mask_diff(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            let is_header = line.starts_with("+++") || line.starts_with("---");
+            match line.chars().next() {
+                Some(marker @ ('+' | '-' | ' ')) if !is_header => format!("{}********\n", marker),
+                _ => line.to_owned() + "\n",
+            }
+        })
+        .collect()
+}
+
+/// The path below the root, or the root itself for the empty path of a single file
+pub fn join_relative(root: &Path, relative: &Path) -> PathBuf {
+    match relative.as_os_str().is_empty() {
+        true => root.to_path_buf(),
+        false => root.join(relative),
+    }
+}
+
+/// Adds the paths of every regular file below the directory the filter keeps, relative to the root
+pub fn collect_files(
+    root: &Path,
+    relative: &Path,
+    files: &mut BTreeSet<PathBuf>,
+    filter: Option<&EntryFilter>,
+) -> ConfigResult<()> {
+    let dir = root.join(relative);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) => return Err(ConfigCliError::FsReadError(err).at(&dir)),
+    };
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(&dir)),
+        };
+        let path = relative.join(entry.file_name());
+        let keeps_dir = filter.is_none_or(|x| x.keeps_dir(&path));
+        let keeps_file = filter.is_none_or(|x| x.keeps_file(&path));
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() && keeps_dir => {
+                collect_files(root, &path, files, filter)?
+            }
+            Ok(file_type) if file_type.is_file() && keeps_file => {
+                files.insert(path);
+            }
+            Ok(_) => (),
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(entry.path())),
+        }
+    }
+    Ok(())
+}
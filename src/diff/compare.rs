@@ -0,0 +1,188 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::{collect_files, join_relative, unified_diff};
+use crate::config::{Config, EntryFilter};
+use crate::error::ConfigCliError;
+use crate::theme::theme_configs;
+use crate::try_read_file;
+use crate::utils::{get_current_theme, ConfigResult};
+use crate::variable::resolve_variables;
+
+/// One side of a comparison
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffSide {
+    /// The deployed file at the target
+    Live,
+    /// The config as a theme deploys it, the current theme if unset
+    Theme {
+        theme: Option<String>,
+        device: Option<String>,
+    },
+}
+
+impl FromStr for DiffSide {
+    type Err = String;
+
+    /// Parses `live`, `<theme>`, `<theme>@<device>` or `@<device>`
+    fn from_str(side: &str) -> Result<Self, Self::Err> {
+        if side == "live" {
+            return Ok(DiffSide::Live);
+        }
+        let (theme, device) = match side.split_once('@') {
+            Some((theme, device)) => (theme, Some(device.to_owned())),
+            None => (side, None),
+        };
+        if theme.is_empty() && device.is_none() {
+            return Err("expected live, <theme>, <theme>@<device> or @<device>".to_owned());
+        }
+        Ok(DiffSide::Theme {
+            theme: Some(theme.to_owned()).filter(|x| !x.is_empty()),
+            device: device.filter(|x| !x.is_empty()),
+        })
+    }
+}
+
+impl std::fmt::Display for DiffSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffSide::Live => write!(f, "live"),
+            DiffSide::Theme { theme, device } => {
+                write!(f, "{}", theme.as_deref().unwrap_or(""))?;
+                match device {
+                    Some(device) => write!(f, "@{}", device),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// The difference of a single file, directories produce one per file that differs
+#[derive(Debug, Serialize)]
+pub struct FileDiff {
+    /// Relative to the config, empty for configs that are a single file
+    pub path: PathBuf,
+    pub diff: String,
+}
+
+/// Compares a config between the live file, themes and device bound variants
+pub fn diff_config(name: String, from: DiffSide, to: DiffSide) -> ConfigResult<Vec<FileDiff>> {
+    let from_config = side_config(&name, &from)?;
+    let to_config = side_config(&name, &to)?;
+
+    // The live file is wherever the other side deploys it, or the current theme if both are live
+    let target = match from_config.iter().chain(to_config.iter()).next() {
+        Some((config, _)) => Some(config.symlink.clone()),
+        None => side_config(
+            &name,
+            &DiffSide::Theme {
+                theme: None,
+                device: None,
+            },
+        )?
+        .map(|x| x.0.symlink),
+    };
+    if from_config.is_none() && to_config.is_none() && target.is_none() {
+        return Err(ConfigCliError::InvalidConfigName(name));
+    }
+
+    // Entries a theme side leaves out are left out of the live side as well
+    let filter = match from_config.iter().chain(to_config.iter()).next() {
+        Some((config, _)) => config.entry_filter()?,
+        None => None,
+    };
+
+    let from_files = side_files(&from, from_config, target.as_deref(), filter.as_ref())?;
+    let to_files = side_files(&to, to_config, target.as_deref(), filter.as_ref())?;
+
+    let paths: BTreeSet<&PathBuf> = from_files.keys().chain(to_files.keys()).collect();
+    let mut diffs = vec![];
+    for path in paths {
+        let old = from_files.get(path);
+        let new = to_files.get(path);
+        let diff = unified_diff(
+            old.map(|x| x.as_slice()).unwrap_or_default(),
+            new.map(|x| x.as_slice()).unwrap_or_default(),
+            &label(&from, &name, path, old.is_some()),
+            &label(&to, &name, path, new.is_some()),
+        );
+        if !diff.is_empty() {
+            diffs.push(FileDiff {
+                path: path.clone(),
+                diff,
+            });
+        }
+    }
+    Ok(diffs)
+}
+
+fn label(side: &DiffSide, name: &str, path: &Path, exists: bool) -> String {
+    if !exists {
+        return "/dev/null".to_owned();
+    }
+    let label = join_relative(Path::new(name), path);
+    format!("{}:{}", side, label.display())
+}
+
+// The config a theme side refers to together with the variables its templates are rendered with
+fn side_config(
+    name: &str,
+    side: &DiffSide,
+) -> ConfigResult<Option<(Config, BTreeMap<String, String>)>> {
+    let (theme, device) = match side {
+        DiffSide::Live => return Ok(None),
+        DiffSide::Theme { theme, device } => (theme.clone(), device.as_deref()),
+    };
+    let theme = match theme {
+        Some(theme) => theme,
+        None => get_current_theme()?,
+    };
+
+    let config = theme_configs(&theme, device)?
+        .into_iter()
+        .find(|x| x.name == name);
+    match config {
+        Some(config) => Ok(Some((config, resolve_variables(&theme, device)?))),
+        None => Ok(None),
+    }
+}
+
+// Every file of the side by its path relative to the config, empty if the side doesn't have the config
+fn side_files(
+    side: &DiffSide,
+    config: Option<(Config, BTreeMap<String, String>)>,
+    target: Option<&Path>,
+    filter: Option<&EntryFilter>,
+) -> ConfigResult<BTreeMap<PathBuf, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+
+    let root = match (side, config, target) {
+        (DiffSide::Live, _, Some(target)) => target.to_path_buf(),
+        (DiffSide::Theme { .. }, Some((config, variables)), _) => {
+            if let Some(content) = config.generated_content(&variables)? {
+                files.insert(PathBuf::new(), content);
+                return Ok(files);
+            }
+            config.conf_location
+        }
+        _ => return Ok(files),
+    };
+    if !root.exists() {
+        return Ok(files);
+    }
+
+    if !root.is_dir() {
+        files.insert(PathBuf::new(), try_read_file!(&root));
+        return Ok(files);
+    }
+    let mut paths = BTreeSet::new();
+    collect_files(&root, Path::new(""), &mut paths, filter)?;
+    for path in paths {
+        let content = try_read_file!(root.join(&path));
+        files.insert(path, content);
+    }
+    Ok(files)
+}
@@ -1,3 +1,41 @@
+use serde::Serialize;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// What kind of failure an error is, every category exits with its own code
+///
+/// | category        | exit code |
+/// |-----------------|-----------|
+/// | user input      | 2         |
+/// | conflict        | 3         |
+/// | filesystem      | 4         |
+/// | git             | 5         |
+/// | package manager | 6         |
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// Unknown names, invalid arguments or a failing hook, fixed by changing the command or the theme
+    UserInput,
+    /// Something is in the way, like an unmanaged file at a target or a leaked secret
+    Conflict,
+    /// Reading, writing or parsing files failed
+    Filesystem,
+    Git,
+    PackageManager,
+}
+
+impl ErrorCategory {
+    pub fn exit_code(self) -> ExitCode {
+        ExitCode::from(match self {
+            ErrorCategory::UserInput => 2,
+            ErrorCategory::Conflict => 3,
+            ErrorCategory::Filesystem => 4,
+            ErrorCategory::Git => 5,
+            ErrorCategory::PackageManager => 6,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum ConfigCliError {
     // FS Errors
@@ -14,17 +52,162 @@ pub enum ConfigCliError {
     StringConversionError(std::str::Utf8Error),
     // Cli Error
     ShellInitError(std::io::Error),
-    GitCommandError(String),
+    GitError(git2::Error),
+    MergeConflict(String),
+    PackageManagerError(String),
     // Internal error
     InvalidThemeName(String),
     InvalidConfigName(String),
     InvalidConfigLocation(String),
     InvalidDependencyName(String),
     ConfigLocationUsed(String),
-    NoPackageWithName(String),
-    DependencyAlreadyExists(String),
     UnableToFindHomeDir,
     NoThemeSelecected,
+    NoPackageManagerFound,
+    UnknownVariable(String),
+    InvalidVariableName(String),
+    TransactionInProgress(String),
+    ThemeCycle(String),
+    EncryptionError(String),
+    DecryptionError(String),
+    SecretLeak(String),
+    HookFailed(String),
+    InvalidDeployMode(String),
+    InvalidBackup(String),
+    UnsupportedSchemaVersion(String),
+    InvalidPattern(String),
+    DeviceCycle(String),
+    UnknownDevice(String),
+    DeviceAlreadyExists(String),
+    InvalidPredicate(String),
+    StillInUse(String),
+    RollbackFailed(Box<ConfigCliError>, Box<ConfigCliError>),
+    /// The error together with what it happened to, like a path or a theme
+    Context(String, Box<ConfigCliError>),
+}
+
+impl ConfigCliError {
+    /// Adds what the error happened to, shown below the message
+    pub fn context(self, context: impl Into<String>) -> Self {
+        ConfigCliError::Context(context.into(), Box::new(self))
+    }
+
+    /// Adds the path the error happened at
+    pub fn at(self, path: impl AsRef<Path>) -> Self {
+        self.context(path.as_ref().display().to_string())
+    }
+
+    /// Adds the theme the error happened in
+    pub fn in_theme(self, theme: &str) -> Self {
+        self.context(format!("theme {}", theme))
+    }
+
+    /// Everything the error was wrapped with, outermost first
+    pub fn contexts(&self) -> Vec<&str> {
+        match self {
+            ConfigCliError::Context(context, err) => {
+                let mut contexts = vec![context.as_str()];
+                contexts.extend(err.contexts());
+                contexts
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        use ConfigCliError::*;
+        use ErrorCategory::*;
+        match self {
+            FsReadError(_)
+            | FsWriteError(_)
+            | FileCreationError(_)
+            | CopyError(_)
+            | DeleteError(_)
+            | SymlinkError(_)
+            | RenameError(_)
+            | DeserializeError(_)
+            | SerializeError(_)
+            | StringConversionError(_)
+            | UnableToFindHomeDir => Filesystem,
+            GitError(_) => Git,
+            ShellInitError(_) | PackageManagerError(_) | NoPackageManagerFound => PackageManager,
+            MergeConflict(_)
+            | ConfigLocationUsed(_)
+            | TransactionInProgress(_)
+            | SecretLeak(_)
+            | StillInUse(_) => Conflict,
+            InvalidThemeName(_)
+            | InvalidConfigName(_)
+            | InvalidConfigLocation(_)
+            | InvalidDependencyName(_)
+            | NoThemeSelecected
+            | UnknownVariable(_)
+            | InvalidVariableName(_)
+            | ThemeCycle(_)
+            | EncryptionError(_)
+            | DecryptionError(_)
+            | HookFailed(_)
+            | InvalidDeployMode(_)
+            | InvalidBackup(_)
+            | UnsupportedSchemaVersion(_)
+            | InvalidPattern(_)
+            | DeviceCycle(_)
+            | UnknownDevice(_)
+            | DeviceAlreadyExists(_)
+            | InvalidPredicate(_) => UserInput,
+            // The files are left half switched, which is worse than whatever caused the rollback
+            RollbackFailed(_, _) => Filesystem,
+            Context(_, err) => err.category(),
+        }
+    }
+
+    /// Stable identifier of the error for machine readable output
+    pub fn kind(&self) -> &'static str {
+        use ConfigCliError::*;
+        match self {
+            FsReadError(_) => "fs_read_error",
+            FsWriteError(_) => "fs_write_error",
+            FileCreationError(_) => "file_creation_error",
+            CopyError(_) => "copy_error",
+            DeleteError(_) => "delete_error",
+            SymlinkError(_) => "symlink_error",
+            RenameError(_) => "rename_error",
+            DeserializeError(_) => "deserialize_error",
+            SerializeError(_) => "serialize_error",
+            StringConversionError(_) => "string_conversion_error",
+            ShellInitError(_) => "shell_init_error",
+            GitError(_) => "git_error",
+            MergeConflict(_) => "merge_conflict",
+            PackageManagerError(_) => "package_manager_error",
+            InvalidThemeName(_) => "invalid_theme_name",
+            InvalidConfigName(_) => "invalid_config_name",
+            InvalidConfigLocation(_) => "invalid_config_location",
+            InvalidDependencyName(_) => "invalid_dependency_name",
+            ConfigLocationUsed(_) => "config_location_used",
+            UnableToFindHomeDir => "unable_to_find_home_dir",
+            NoThemeSelecected => "no_theme_selected",
+            NoPackageManagerFound => "no_package_manager_found",
+            UnknownVariable(_) => "unknown_variable",
+            InvalidVariableName(_) => "invalid_variable_name",
+            TransactionInProgress(_) => "transaction_in_progress",
+            ThemeCycle(_) => "theme_cycle",
+            EncryptionError(_) => "encryption_error",
+            DecryptionError(_) => "decryption_error",
+            SecretLeak(_) => "secret_leak",
+            HookFailed(_) => "hook_failed",
+            InvalidDeployMode(_) => "invalid_deploy_mode",
+            InvalidBackup(_) => "invalid_backup",
+            UnsupportedSchemaVersion(_) => "unsupported_schema_version",
+            InvalidPattern(_) => "invalid_pattern",
+            DeviceCycle(_) => "device_cycle",
+            UnknownDevice(_) => "unknown_device",
+            DeviceAlreadyExists(_) => "device_already_exists",
+            InvalidPredicate(_) => "invalid_predicate",
+            StillInUse(_) => "still_in_use",
+            RollbackFailed(_, _) => "rollback_failed",
+            Context(_, err) => err.kind(),
+        }
+    }
 }
 
 impl std::fmt::Display for ConfigCliError {
@@ -42,16 +225,52 @@ impl std::fmt::Display for ConfigCliError {
             DeserializeError(err) => write!(f, "TOML Parse Error while deserializing: \n{}", err),
             StringConversionError(err) => write!(f, "Invalid String: \n{}", err),
             ShellInitError(err) => write!(f, "Failed to initialize shell: \n{}", err),
-            GitCommandError(err) => write!(f, "Git Command Error: \n{}", err),
+            GitError(err) => write!(f, "Git Error: \n{}", err),
+            MergeConflict(err) => write!(f, "Merge conflict in theme: \n{}", err),
+            PackageManagerError(err) => write!(f, "Package Manager Error: \n{}", err),
             InvalidThemeName(err) => write!(f, "Invalid Theme Name:  \n{}", err),
             InvalidConfigName(err) => write!(f, "Invalid Config Name: \n{}", err),
             InvalidDependencyName(err) => write!(f, "Invalid Dependency Name: \n{}", err),
             InvalidConfigLocation(err) => write!(f, "Invalid Config Location: \n{}", err),
             ConfigLocationUsed(err) => write!(f, "Config Location {} already used", err),
-            NoPackageWithName(err) => write!(f, "No Package with name: \n{}", err),
-            DependencyAlreadyExists(err) => write!(f, "Dependency already exists: \n{}", err),
             UnableToFindHomeDir => write!(f, "Unable to find home directory"),
             NoThemeSelecected => write!(f, "No theme selecected"),
+            NoPackageManagerFound => write!(f, "No supported package manager found"),
+            UnknownVariable(err) => write!(
+                f,
+                "Unknown template variable, or a secret this machine can't decrypt: \n{}",
+                err
+            ),
+            InvalidVariableName(err) => write!(f, "Invalid Variable Name: \n{}", err),
+            TransactionInProgress(err) => write!(
+                f,
+                "Another theme switch is in progress or was interrupted, see `theme recover`: \n{}",
+                err
+            ),
+            ThemeCycle(err) => write!(f, "Theme inherits from itself: \n{}", err),
+            EncryptionError(err) => write!(f, "Encryption Error: \n{}", err),
+            DecryptionError(err) => write!(f, "Decryption Error: \n{}", err),
+            SecretLeak(err) => write!(
+                f,
+                "Refusing to commit, a secret is in plaintext in: \n{}",
+                err
+            ),
+            HookFailed(err) => write!(f, "Hook failed: \n{}", err),
+            InvalidDeployMode(err) => write!(f, "Invalid Deploy Mode: \n{}", err),
+            InvalidBackup(err) => write!(f, "Invalid Backup: \n{}", err),
+            UnsupportedSchemaVersion(err) => write!(f, "Unsupported Schema Version: \n{}", err),
+            InvalidPattern(err) => write!(f, "Invalid Pattern: \n{}", err),
+            DeviceCycle(err) => write!(f, "Device inherits from itself: \n{}", err),
+            UnknownDevice(err) => write!(f, "Unknown device: \n{}", err),
+            DeviceAlreadyExists(err) => write!(f, "Device already exists: \n{}", err),
+            InvalidPredicate(err) => write!(f, "Invalid condition: \n{}", err),
+            StillInUse(err) => write!(f, "Still in use, pass --force to remove anyway: \n{}", err),
+            RollbackFailed(err, rollback_err) => write!(
+                f,
+                "{}\nRollback failed, original files are kept in the transaction directory: \n{}",
+                err, rollback_err
+            ),
+            Context(context, err) => write!(f, "{}\n  at {}", err, context),
         }
     }
 }
@@ -71,16 +290,36 @@ impl std::error::Error for ConfigCliError {
             DeserializeError(x) => Some(x),
             StringConversionError(x) => Some(x),
             ShellInitError(x) => Some(x),
-            GitCommandError(_) => None,
+            GitError(x) => Some(x),
+            MergeConflict(_) => None,
+            PackageManagerError(_) => None,
             InvalidThemeName(_) => None,
             InvalidConfigName(_) => None,
             InvalidDependencyName(_) => None,
             InvalidConfigLocation(_) => None,
             ConfigLocationUsed(_) => None,
-            NoPackageWithName(_) => None,
-            DependencyAlreadyExists(_) => None,
             UnableToFindHomeDir => None,
             NoThemeSelecected => None,
+            NoPackageManagerFound => None,
+            UnknownVariable(_) => None,
+            InvalidVariableName(_) => None,
+            TransactionInProgress(_) => None,
+            ThemeCycle(_) => None,
+            EncryptionError(_) => None,
+            DecryptionError(_) => None,
+            SecretLeak(_) => None,
+            HookFailed(_) => None,
+            InvalidDeployMode(_) => None,
+            InvalidBackup(_) => None,
+            UnsupportedSchemaVersion(_) => None,
+            InvalidPattern(_) => None,
+            DeviceCycle(_) => None,
+            UnknownDevice(_) => None,
+            DeviceAlreadyExists(_) => None,
+            InvalidPredicate(_) => None,
+            StillInUse(_) => None,
+            RollbackFailed(x, _) => Some(x.as_ref()),
+            Context(_, x) => Some(x.as_ref()),
         }
     }
 }
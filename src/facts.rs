@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::os::unix::fs::MetadataExt;
+
+/// Facts about the machine that are available as template variables
+pub fn builtin_facts() -> BTreeMap<String, String> {
+    let mut facts = BTreeMap::new();
+    facts.insert("hostname".to_owned(), hostname());
+    facts.insert("username".to_owned(), username());
+    facts.insert("os".to_owned(), os());
+    facts.insert("arch".to_owned(), arch());
+    facts
+}
+
+pub fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .map(|x| x.trim().to_owned())
+        .unwrap_or_default()
+}
+
+pub fn username() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .unwrap_or_else(|_| username_from_passwd().unwrap_or_default())
+}
+
+// Looks up the name of the uid owning this process
+fn username_from_passwd() -> Option<String> {
+    let uid = std::fs::metadata("/proc/self").ok()?.uid().to_string();
+    std::fs::read_to_string("/etc/passwd")
+        .ok()?
+        .lines()
+        .map(|x| x.split(':').collect::<Vec<&str>>())
+        .find(|x| x.get(2) == Some(&uid.as_str()))
+        .map(|x| x[0].to_owned())
+}
+
+fn os_release(key: &str) -> Option<String> {
+    let prefix = key.to_owned() + "=";
+    std::fs::read_to_string("/etc/os-release")
+        .ok()?
+        .lines()
+        .find_map(|x| x.strip_prefix(&prefix))
+        .map(|x| x.trim_matches('"').to_owned())
+}
+
+/// The distribution id from os-release, like `arch` or `debian`
+pub fn os() -> String {
+    os_release("ID").unwrap_or(std::env::consts::OS.to_owned())
+}
+
+/// The distribution version from os-release, empty for rolling releases
+pub fn os_version() -> String {
+    os_release("VERSION_ID").unwrap_or_default()
+}
+
+/// The CPU architecture of the machine from `uname -m`, like `x86_64` or `aarch64`
+///
+/// Falls back to the architecture this was built for when `uname` can't be run.
+pub fn arch() -> String {
+    std::process::Command::new("uname")
+        .arg("-m")
+        .output()
+        .ok()
+        .filter(|x| x.status.success())
+        .and_then(|x| String::from_utf8(x.stdout).ok())
+        .map(|x| x.trim().to_owned())
+        .filter(|x| !x.is_empty())
+        .unwrap_or(std::env::consts::ARCH.to_owned())
+}
+
+pub fn machine_id() -> String {
+    std::fs::read_to_string("/etc/machine-id")
+        .or_else(|_| std::fs::read_to_string("/var/lib/dbus/machine-id"))
+        .map(|x| x.trim().to_owned())
+        .unwrap_or_default()
+}
+
+/// Whether any power supply of the machine is a battery, which is a good hint for a laptop
+pub fn has_battery() -> bool {
+    std::fs::read_dir("/sys/class/power_supply")
+        .map(|entries| {
+            entries.flatten().any(|entry| {
+                std::fs::read_to_string(entry.path().join("type"))
+                    .is_ok_and(|x| x.trim() == "Battery")
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// The kind of graphical session, `wayland`, `x11` or `tty` without one
+pub fn session_type() -> String {
+    match std::env::var("XDG_SESSION_TYPE") {
+        Ok(session) if !session.is_empty() => session,
+        _ if std::env::var_os("WAYLAND_DISPLAY").is_some() => "wayland".to_owned(),
+        _ if std::env::var_os("DISPLAY").is_some() => "x11".to_owned(),
+        _ => "tty".to_owned(),
+    }
+}
+
+/// The vendors of the graphics cards, like `intel nvidia`, unknown vendors by their PCI id
+pub fn gpu_vendors() -> String {
+    let mut vendors: Vec<String> = vec![];
+    if let Ok(entries) = std::fs::read_dir("/sys/class/drm") {
+        for entry in entries.flatten() {
+            let Ok(id) = std::fs::read_to_string(entry.path().join("device/vendor")) else {
+                continue;
+            };
+            let vendor = match id.trim() {
+                "0x10de" => "nvidia",
+                "0x1002" => "amd",
+                "0x8086" => "intel",
+                other => other,
+            };
+            if !vendors.iter().any(|x| x == vendor) {
+                vendors.push(vendor.to_owned());
+            }
+        }
+    }
+    vendors.sort();
+    vendors.join(" ")
+}
@@ -1,46 +1,254 @@
-use crate::{dependency::DependencyFile, error::ConfigCliError, try_git, try_read_and_parse};
+use git2::build::RepoBuilder;
+use git2::{
+    AnnotatedCommit, Cred, CredentialType, FetchOptions, IndexAddOption, PushOptions,
+    RemoteCallbacks, Repository, ResetType, StatusOptions,
+};
+use std::path::Path;
+
+use crate::dependency::{install_dependencies, Installation};
+use crate::error::ConfigCliError;
+use crate::secret::check_for_leaks;
+use crate::theme::list_themes;
+use crate::try_git;
 use crate::{get_base_dir, get_current_theme, utils::ConfigResult};
-use std::process::Command;
 
-pub fn install_theme(url: String) -> ConfigResult<()> {
-    let base_dir = get_base_dir()?;
-    try_git!("clone ".to_owned() + &url, &base_dir);
+const REMOTE: &str = "origin";
+
+pub fn install_theme(url: String) -> ConfigResult<Option<Installation>> {
+    // Themes are named after the last segment of the url, like git clone does
+    let theme = url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(&url)
+        .trim_end_matches(".git")
+        .to_owned();
+    let theme_path = get_base_dir()? + &theme;
 
-    let dependencies = try_read_and_parse!(base_dir + "dependencies.toml", DependencyFile);
-    for dependency in dependencies.globals {
-        dependency.install()?;
+    if Path::new(&theme_path).exists() {
+        return Err(ConfigCliError::InvalidThemeName(theme));
     }
-    Ok(())
-}
 
-/*
-fn git_init(url: String) -> ConfigResult<()> {
-    let theme_dir = get_base_dir()? + &get_current_theme()?;
-    try_git!("init", &theme_dir);
-    try_git!("remote add origin ".to_owned() + &url, &theme_dir);
-    Ok(())
+    try_git!(RepoBuilder::new()
+        .fetch_options(fetch_options())
+        .clone(&url, Path::new(&theme_path)));
+
+    install_dependencies(&theme, None)
 }
-*/
 
 pub fn set_url(url: String) -> ConfigResult<()> {
-    let theme_dir = get_base_dir()? + &get_current_theme()?;
-    try_git!("remote set-url origin ".to_owned() + &url, &theme_dir);
+    let repo = open_theme_repo()?;
+    match repo.find_remote(REMOTE) {
+        Ok(_) => try_git!(repo.remote_set_url(REMOTE, &url)),
+        Err(_) => {
+            try_git!(repo.remote(REMOTE, &url));
+        }
+    }
     Ok(())
 }
 
 pub fn pull() -> ConfigResult<()> {
-    try_git!("pull", get_base_dir()? + &get_current_theme()?);
-    Ok(())
+    let repo = open_theme_repo()?;
+    let branch = current_branch(&repo)?;
+
+    let mut remote = try_git!(repo.find_remote(REMOTE));
+    try_git!(remote.fetch(&[&branch], Some(&mut fetch_options()), None));
+
+    let fetch_head = try_git!(repo.find_reference("FETCH_HEAD"));
+    let fetch_commit = try_git!(repo.reference_to_annotated_commit(&fetch_head));
+    merge(&repo, &branch, &fetch_commit)
 }
 
 pub fn push(commit_message: Option<String>) -> ConfigResult<()> {
+    // Every theme in the store is checked, not only the ones the current theme inherits from
+    for theme in list_themes()? {
+        let name = Path::new(&theme).file_name().unwrap_or_default();
+        check_for_leaks(&name.to_string_lossy())?;
+    }
+
+    let repo = open_theme_repo()?;
+    let branch = current_branch(&repo)?;
+
+    commit_all(
+        &repo,
+        &commit_message.unwrap_or("Automated commit from config_manager".to_owned()),
+    )?;
+
+    // Rejected references are only reported through this callback
+    let mut rejection: Option<String> = None;
+    {
+        let mut callbacks = remote_callbacks();
+        callbacks.push_update_reference(|reference, status| {
+            if let Some(status) = status {
+                rejection = Some(format!("{}: {}", reference, status));
+            }
+            Ok(())
+        });
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(callbacks);
+
+        let mut remote = try_git!(repo.find_remote(REMOTE));
+        let refspec = format!("refs/heads/{}:refs/heads/{}", branch, branch);
+        try_git!(remote.push(&[&refspec], Some(&mut push_options)));
+    }
+
+    match rejection {
+        Some(rejection) => Err(ConfigCliError::GitError(git2::Error::from_str(&format!(
+            "Push rejected for {}",
+            rejection
+        )))),
+        None => Ok(()),
+    }
+}
+
+fn open_theme_repo() -> ConfigResult<Repository> {
     let theme_dir = get_base_dir()? + &get_current_theme()?;
-    try_git!("add .", &theme_dir);
-    try_git!(
-        "commit -m ".to_owned()
-            + &commit_message.unwrap_or("Automated commit from config_manager".to_owned()),
-        &theme_dir
-    );
-    try_git!("push", &theme_dir);
+    Ok(try_git!(Repository::open(theme_dir)))
+}
+
+// Read from the symbolic target of HEAD, so a branch without commits yet has a name too
+fn current_branch(repo: &Repository) -> ConfigResult<String> {
+    let head = try_git!(repo.find_reference("HEAD"));
+    match head
+        .symbolic_target()
+        .and_then(|x| x.strip_prefix("refs/heads/"))
+    {
+        Some(branch) => Ok(branch.to_owned()),
+        None => Err(ConfigCliError::GitError(git2::Error::from_str(
+            "HEAD is not on a branch",
+        ))),
+    }
+}
+
+// Stages every change in the work tree, including deletions, and commits it if anything changed
+fn commit_all(repo: &Repository, message: &str) -> ConfigResult<()> {
+    let mut index = try_git!(repo.index());
+    try_git!(index.add_all(["*"], IndexAddOption::DEFAULT, None));
+    try_git!(index.update_all(["*"], None));
+    try_git!(index.write());
+
+    let tree_id = try_git!(index.write_tree());
+    let parent = match repo.head() {
+        Ok(head) => Some(try_git!(head.peel_to_commit())),
+        // Nothing has been committed on the branch yet
+        Err(_) => None,
+    };
+
+    if parent.as_ref().map(|x| x.tree_id()) == Some(tree_id) {
+        return Ok(());
+    }
+
+    let tree = try_git!(repo.find_tree(tree_id));
+    let signature = try_git!(repo.signature());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    try_git!(repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents
+    ));
+    Ok(())
+}
+
+fn merge(repo: &Repository, branch: &str, fetch_commit: &AnnotatedCommit) -> ConfigResult<()> {
+    let (analysis, _) = try_git!(repo.merge_analysis(&[fetch_commit]));
+
+    if analysis.is_up_to_date() {
+        return Ok(());
+    }
+
+    // Neither path may touch edits that were not pushed yet
+    let mut status_options = StatusOptions::new();
+    status_options.include_untracked(false);
+    let dirty = try_git!(repo.statuses(Some(&mut status_options)));
+    if !dirty.is_empty() {
+        let paths: Vec<String> = dirty
+            .iter()
+            .filter_map(|x| x.path().map(str::to_owned))
+            .collect();
+        return Err(ConfigCliError::GitError(git2::Error::from_str(&format!(
+            "The theme has uncommitted changes, push or revert them before pulling:\n{}",
+            paths.join("\n")
+        ))));
+    }
+
+    if analysis.is_fast_forward() {
+        // Checked out against the old HEAD, so files are only updated where nothing changed
+        let target = try_git!(repo.find_object(fetch_commit.id(), None));
+        try_git!(repo.checkout_tree(&target, Some(git2::build::CheckoutBuilder::new().safe())));
+        let ref_name = format!("refs/heads/{}", branch);
+        let mut reference = try_git!(repo.find_reference(&ref_name));
+        try_git!(reference.set_target(fetch_commit.id(), "Fast-forward"));
+        try_git!(repo.set_head(&ref_name));
+        return Ok(());
+    }
+
+    try_git!(repo.merge(&[fetch_commit], None, None));
+
+    let mut index = try_git!(repo.index());
+    if index.has_conflicts() {
+        let conflicts: Vec<String> = try_git!(index.conflicts())
+            .filter_map(|x| x.ok())
+            .filter_map(|x| x.our.or(x.their))
+            .map(|x| String::from_utf8_lossy(&x.path).to_string())
+            .collect();
+        // The work tree was clean, so going back to HEAD only drops the half done merge
+        let head = try_git!(try_git!(repo.head()).peel_to_commit());
+        try_git!(repo.reset(head.as_object(), ResetType::Hard, None));
+        try_git!(repo.cleanup_state());
+        return Err(ConfigCliError::MergeConflict(conflicts.join("\n")));
+    }
+
+    let tree_id = try_git!(index.write_tree());
+    let tree = try_git!(repo.find_tree(tree_id));
+    let signature = try_git!(repo.signature());
+    let local_commit = try_git!(try_git!(repo.head()).peel_to_commit());
+    let remote_commit = try_git!(repo.find_commit(fetch_commit.id()));
+    try_git!(repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &format!("Merge {}/{} into {}", REMOTE, branch, branch),
+        &tree,
+        &[&local_commit, &remote_commit]
+    ));
+    try_git!(repo.cleanup_state());
     Ok(())
 }
+
+fn fetch_options() -> FetchOptions<'static> {
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(remote_callbacks());
+    fetch_options
+}
+
+// Tries the ssh agent, then the configured git credential helpers
+fn remote_callbacks() -> RemoteCallbacks<'static> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut tried_agent = false;
+    let mut tried_helper = false;
+
+    callbacks.credentials(move |url, username, allowed| {
+        if allowed.contains(CredentialType::SSH_KEY) && !tried_agent {
+            tried_agent = true;
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && !tried_helper {
+            tried_helper = true;
+            let config = git2::Config::open_default()?;
+            return Cred::credential_helper(&config, url, username);
+        }
+
+        if allowed.contains(CredentialType::DEFAULT) {
+            return Cred::default();
+        }
+
+        Err(git2::Error::from_str(
+            "No usable credentials for the remote",
+        ))
+    });
+    callbacks
+}
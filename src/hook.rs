@@ -0,0 +1,191 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::ConfigCliError;
+use crate::theme::{load_manifest, theme_chain};
+use crate::utils::{get_base_dir, ConfigResult};
+
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStage {
+    PreApply,
+    PostApply,
+    PreRemove,
+    PostRemove,
+}
+
+impl HookStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookStage::PreApply => "pre_apply",
+            HookStage::PostApply => "post_apply",
+            HookStage::PreRemove => "pre_remove",
+            HookStage::PostRemove => "post_remove",
+        }
+    }
+}
+
+/// What happens when a hook exits with a non zero status
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HookPolicy {
+    /// Stops the operation, a theme switch gets rolled back
+    #[default]
+    Abort,
+    /// Reports the failure and carries on
+    Warn,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Hook {
+    /// Run with `sh -c` from the theme directory
+    pub command: String,
+    #[serde(default)]
+    pub on_failure: HookPolicy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Hooks {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_apply: Vec<Hook>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_apply: Vec<Hook>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_remove: Vec<Hook>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_remove: Vec<Hook>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre_apply.is_empty()
+            && self.post_apply.is_empty()
+            && self.pre_remove.is_empty()
+            && self.post_remove.is_empty()
+    }
+
+    pub fn stage(&self, stage: HookStage) -> &Vec<Hook> {
+        match stage {
+            HookStage::PreApply => &self.pre_apply,
+            HookStage::PostApply => &self.post_apply,
+            HookStage::PreRemove => &self.pre_remove,
+            HookStage::PostRemove => &self.post_remove,
+        }
+    }
+
+    pub fn stage_mut(&mut self, stage: HookStage) -> &mut Vec<Hook> {
+        match stage {
+            HookStage::PreApply => &mut self.pre_apply,
+            HookStage::PostApply => &mut self.post_apply,
+            HookStage::PreRemove => &mut self.pre_remove,
+            HookStage::PostRemove => &mut self.post_remove,
+        }
+    }
+}
+
+/// What a hook is run for, passed to it as environment variables
+pub struct HookContext<'a> {
+    pub theme: &'a str,
+    /// The config name and its target, unset for theme hooks
+    pub config: Option<(&'a str, &'a Path)>,
+}
+
+/// The captured result of a hook that was run
+#[derive(Serialize, Debug, Clone)]
+pub struct HookRun {
+    pub stage: HookStage,
+    pub theme: String,
+    pub config: Option<String>,
+    pub command: String,
+    pub on_failure: HookPolicy,
+    pub success: bool,
+    /// None if the hook was killed by a signal
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs the hooks of the stage in order, stopping at the first failing hook with the abort policy
+pub fn run_hooks(
+    hooks: &Hooks,
+    stage: HookStage,
+    context: &HookContext,
+    runs: &mut Vec<HookRun>,
+) -> ConfigResult<()> {
+    for hook in hooks.stage(stage) {
+        let run = run_hook(hook, stage, context)?;
+        let failed = !run.success && hook.on_failure == HookPolicy::Abort;
+        runs.push(run);
+
+        if let Some(run) = runs.last().filter(|_| failed) {
+            let owner = run.config.clone().unwrap_or(run.theme.clone());
+            let mut message = format!(
+                "{} hook `{}` of {} exited with {}",
+                stage.name(),
+                run.command,
+                owner,
+                run.exit_code
+                    .map(|x| x.to_string())
+                    .unwrap_or("a signal".to_owned()),
+            );
+            if !run.stderr.trim().is_empty() {
+                message += &format!("\n{}", run.stderr.trim_end());
+            }
+            return Err(ConfigCliError::HookFailed(message));
+        }
+    }
+    Ok(())
+}
+
+fn run_hook(hook: &Hook, stage: HookStage, context: &HookContext) -> ConfigResult<HookRun> {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(&hook.command)
+        .current_dir(get_base_dir()? + context.theme)
+        .env("CONFIG_MANAGER_HOOK", stage.name())
+        .env("CONFIG_MANAGER_THEME", context.theme);
+    if let Some((name, target)) = context.config {
+        command
+            .env("CONFIG_MANAGER_CONFIG", name)
+            .env("CONFIG_MANAGER_TARGET", target);
+    }
+
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(err) => {
+            return Err(ConfigCliError::HookFailed(format!(
+                "{} hook `{}` could not be started: {}",
+                stage.name(),
+                hook.command,
+                err
+            )))
+        }
+    };
+
+    Ok(HookRun {
+        stage,
+        theme: context.theme.to_owned(),
+        config: context.config.map(|x| x.0.to_owned()),
+        command: hook.command.clone(),
+        on_failure: hook.on_failure,
+        success: output.status.success(),
+        exit_code: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    })
+}
+
+/// Runs the theme hooks of every layer of the chain, starting at the root
+pub fn run_theme_hooks(theme: &str, stage: HookStage, runs: &mut Vec<HookRun>) -> ConfigResult<()> {
+    for layer in theme_chain(theme)? {
+        let context = HookContext {
+            theme: &layer,
+            config: None,
+        };
+        run_hooks(&load_manifest(&layer)?.hooks, stage, &context, runs)?;
+    }
+    Ok(())
+}
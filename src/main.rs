@@ -1,78 +1,108 @@
+mod backup;
 mod cli;
 mod config;
 mod dependency;
 mod device;
+mod diff;
 mod error;
+mod facts;
 mod git;
+mod hook;
+mod package_manager;
+mod predicate;
 mod print;
+mod secret;
+mod status;
+mod template;
 mod theme;
 mod utils;
+mod variable;
 
-use cli::ConfigCli;
-use config::Config;
-use dependency::Dependency;
+use backup::{list_backups, restore_backup, set_retention, BackupInfo};
+use cli::{ConfigCli, ConfigSubCommands};
+use error::ConfigCliError;
 use utils::*;
 
-use config::{add_config, list_configs, remove_config};
-use dependency::{add_dependency, list_dependencies, remove_dependency};
-use device::{list_devices, remove_device, use_device};
+use config::{
+    add_config, add_config_hook, encrypt_config, list_configs, list_device_configs,
+    record_config_attributes, remove_config, set_config_mode, set_config_when, sync_configs,
+    AddOptions, Config, DeviceConfigs, EntryGlobs, SyncedFile,
+};
+use dependency::{add_dependency, list_dependencies, remove_dependency, Installation};
+use device::{
+    add_device, current_device, list_devices, remove_device, rename_device, set_device_group,
+    set_device_match, set_device_parent, set_package_manager, show_device, use_device,
+    DeviceDetection, DeviceInfo, DeviceMatch,
+};
+use diff::{diff_config, FileDiff};
 use git::*;
+use hook::{Hook, HookRun};
+use secret::{add_recipient, public_key};
+use status::{status, StatusReport};
 use theme::*;
+use variable::{list_variables, remove_variable, set_variable};
 
 use clap::Parser;
-use serde::{Deserialize, Serialize};
-use std::path::Path;
-
-#[derive(Serialize, Deserialize)]
-enum ThemeStatus {
-    InProgress,
-    Finished,
-    GitCommited,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Device(String);
-
-#[derive(Serialize, Deserialize)]
-struct Theme {
-    name: String,
-    device: Device,
-    global_dependencies: Vec<Dependency>,
-    configs: Vec<Config>,
-    status: ThemeStatus,
-}
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 #[derive(Debug)]
 enum CommandResult {
     DependencyThemeList(ConfigResult<Vec<String>>),
-    ConfigList(ConfigResult<Vec<Config>>),
+    ConfigList(ConfigResult<Vec<EffectiveConfig>>),
+    DeviceConfigList(ConfigResult<DeviceConfigs>),
     AddRemove(ConfigResult<()>),
+    ThemePlan(ConfigResult<ThemePlan>),
+    Status(ConfigResult<StatusReport>),
+    VariableList(ConfigResult<BTreeMap<String, String>>),
+    Hooks(ConfigResult<Vec<HookRun>>),
+    ThemeSwitch(ConfigResult<ThemeSwitch>),
+    Installation(ConfigResult<Option<Installation>>),
+    Synced(ConfigResult<Vec<SyncedFile>>),
+    Diff(ConfigResult<Vec<FileDiff>>),
+    BackupList(ConfigResult<Vec<BackupInfo>>),
+    Restored(ConfigResult<Option<String>>),
+    DeviceDetection(ConfigResult<DeviceDetection>),
+    DeviceInfo(ConfigResult<DeviceInfo>),
+    DeviceRemoved(ConfigResult<Vec<Config>>),
 }
 
-fn main() -> ConfigResult<()> {
+fn main() -> ExitCode {
     let config_cli = ConfigCli::parse();
-    let base_dir: String = get_base_dir()?;
 
-    match Path::exists(Path::new(&base_dir)) {
-        true => (),
-        false => match std::fs::create_dir(base_dir.clone()) {
-            Ok(_) => (),
-            Err(x) => panic!("{}", x),
-        },
+    let result = match run(config_cli.command, config_cli.store) {
+        Ok(result) => result,
+        Err(err) => CommandResult::AddRemove(Err(err)),
+    };
+    result.print(config_cli.output)
+}
+
+fn create_dir_if_missing(dir: &Path) -> ConfigResult<()> {
+    match Path::exists(dir) {
+        true => Ok(()),
+        false => std::fs::create_dir_all(dir)
+            .map_err(|err| ConfigCliError::FileCreationError(err).at(dir)),
     }
+}
+
+fn run(options: ConfigSubCommands, store: Option<PathBuf>) -> ConfigResult<CommandResult> {
+    if let Some(store) = store {
+        set_store_dir(store)?;
+    }
+    // The state directory is named after the canonical store, so the store has to exist first
+    let base_dir = PathBuf::from(get_base_dir()?);
+    create_dir_if_missing(&base_dir)?;
+    let state_dir = get_state_dir()?;
+    create_dir_if_missing(&state_dir)?;
+    migrate_legacy_state()?;
 
-    let current_theme_path = &(base_dir + "/current_theme.toml");
-    let current_theme_path = Path::new(current_theme_path);
-    match Path::exists(current_theme_path) {
-        true => (),
-        false => match std::fs::File::create(current_theme_path) {
-            Ok(_) => (),
-            Err(x) => panic!("{}", x),
-        },
+    let current_theme_path = state_dir.join("current_theme.toml");
+    if !Path::exists(&current_theme_path) {
+        try_create_file!(current_theme_path);
     }
 
     use cli::ConfigSubCommands::*;
-    let options = config_cli.command;
     let result: CommandResult = match options {
         Dependency { action, .. } => {
             use cli::DependencyActions::*;
@@ -84,13 +114,15 @@ fn main() -> ConfigResult<()> {
                 Add {
                     dependency_name,
                     config_name,
+                    device,
                 } => CommandResult::AddRemove(add_dependency(
                     theme_name,
                     config_name,
+                    device,
                     dependency_name,
                 )),
                 List { config_name } => {
-                    CommandResult::DependencyThemeList(list_dependencies(config_name, theme_name))
+                    CommandResult::DependencyThemeList(list_dependencies(theme_name, config_name))
                 }
             }
         }
@@ -99,41 +131,165 @@ fn main() -> ConfigResult<()> {
             let theme_name = get_current_theme()?;
             match action {
                 Remove { config_name } => {
-                    CommandResult::AddRemove(remove_config(config_name, theme_name))
+                    CommandResult::Hooks(remove_config(config_name, theme_name))
                 }
                 Add {
                     config_name,
                     file,
                     device_name,
+                    mode,
+                    secret,
+                    target_root,
+                    when,
+                    include,
+                    exclude,
                 } => CommandResult::AddRemove(add_config(
                     config_name,
-                    device_name.clone(),
+                    device_name,
+                    absolute_path(file)?,
+                    AddOptions { mode, secret, when },
+                    target_root.map(absolute_path).transpose()?,
+                    EntryGlobs { include, exclude },
+                )),
+                AddHook {
+                    config_name,
+                    stage,
+                    command,
+                    on_failure,
+                } => CommandResult::AddRemove(add_config_hook(
+                    config_name,
+                    theme_name,
+                    stage,
+                    Hook {
+                        command,
+                        on_failure,
+                    },
+                )),
+                RecordAttributes { config_name, owner } => CommandResult::AddRemove(
+                    record_config_attributes(config_name, theme_name, owner),
+                ),
+                SetWhen { config_name, when } => {
+                    CommandResult::AddRemove(set_config_when(config_name, theme_name, when))
+                }
+                SetMode { config_name, mode } => {
+                    CommandResult::AddRemove(set_config_mode(config_name, theme_name, mode))
+                }
+                Sync {
+                    config_name,
+                    interactive,
+                    dry_run,
+                    show_secrets,
+                    device,
+                    ..
+                } => CommandResult::Synced(sync_configs(
                     theme_name,
-                    file.to_path_buf(),
+                    config_name,
+                    device,
+                    interactive,
+                    dry_run,
+                    show_secrets,
                 )),
-                List { device_name } => {
-                    CommandResult::ConfigList(list_configs(theme_name, device_name))
+                Encrypt { config_name } => {
+                    CommandResult::AddRemove(encrypt_config(config_name, theme_name))
                 }
+                List { device_name } => match device_name {
+                    Some(device) => {
+                        CommandResult::DeviceConfigList(list_device_configs(theme_name, device))
+                    }
+                    None => CommandResult::ConfigList(list_configs(theme_name)),
+                },
             }
         }
         Device { action } => {
             use cli::DeviceActions::*;
             match action {
-                Remove { name } => CommandResult::AddRemove(remove_device(name)),
-                Use { name } => CommandResult::AddRemove(use_device(name)),
+                Add { name } => CommandResult::AddRemove(add_device(name)),
+                Remove { name, force } => CommandResult::DeviceRemoved(remove_device(name, force)),
+                Rename { name, new_name } => {
+                    CommandResult::DependencyThemeList(rename_device(name, new_name))
+                }
+                Show { name } => CommandResult::DeviceInfo(show_device(name)),
+                Use { name } => CommandResult::Installation(use_device(name)),
                 List => CommandResult::DependencyThemeList(list_devices()),
+                SetPackageManager {
+                    name,
+                    package_manager,
+                } => CommandResult::AddRemove(set_package_manager(name, package_manager)),
+                SetMatch {
+                    name,
+                    hostname,
+                    machine_id,
+                    os,
+                    os_version,
+                    arch,
+                    battery,
+                } => CommandResult::AddRemove(set_device_match(
+                    name,
+                    DeviceMatch {
+                        hostname,
+                        machine_id,
+                        os,
+                        os_version,
+                        arch,
+                        battery,
+                    },
+                )),
+                SetGroup { name, devices } => {
+                    CommandResult::AddRemove(set_device_group(name, devices))
+                }
+                SetParent { name, parent } => {
+                    CommandResult::AddRemove(set_device_parent(name, parent))
+                }
+                Current => CommandResult::DeviceDetection(current_device()),
             }
         }
         Theme { action, .. } => {
             use cli::ThemeActions::*;
             match action {
-                Remove { name } => CommandResult::AddRemove(remove_theme(name)),
-                Create { name, base } => CommandResult::AddRemove(create_theme(name, base)),
+                Remove { name, force } => CommandResult::AddRemove(remove_theme(name, force)),
+                Create { name, parents } => CommandResult::AddRemove(create_theme(name, parents)),
+                SetParents { name, parents } => {
+                    CommandResult::AddRemove(set_parents(name, parents))
+                }
+                Use {
+                    name,
+                    force,
+                    dry_run: true,
+                    device,
+                    target_root,
+                } => CommandResult::ThemePlan(plan_theme(
+                    name,
+                    force,
+                    device,
+                    target_root.map(absolute_path).transpose()?,
+                )),
                 Use {
                     name,
                     force,
                     device,
-                } => CommandResult::AddRemove(use_theme(name, force, device)),
+                    target_root,
+                    ..
+                } => CommandResult::ThemeSwitch(use_theme(
+                    name,
+                    force,
+                    device,
+                    target_root.map(absolute_path).transpose()?,
+                )),
+                AddHook {
+                    name,
+                    stage,
+                    command,
+                    on_failure,
+                } => CommandResult::AddRemove(add_theme_hook(
+                    name,
+                    stage,
+                    Hook {
+                        command,
+                        on_failure,
+                    },
+                )),
+                Migrate { names } => CommandResult::DependencyThemeList(migrate_themes(names)),
+                Recover => CommandResult::DependencyThemeList(recover_theme_switch()),
                 List => CommandResult::DependencyThemeList(list_themes()),
             }
         }
@@ -141,13 +297,59 @@ fn main() -> ConfigResult<()> {
             use cli::GitActions::*;
             match action {
                 SetUrl { url } => CommandResult::AddRemove(set_url(url)),
-                InstallTheme { url } => CommandResult::AddRemove(install_theme(url)),
+                InstallTheme { url } => CommandResult::Installation(install_theme(url)),
                 Push { commit_message } => CommandResult::AddRemove(push(commit_message)),
                 Pull => CommandResult::AddRemove(pull()),
             }
         }
+        Status {
+            device,
+            target_root,
+        } => CommandResult::Status(status(device, target_root.map(absolute_path).transpose()?)),
+        Variable { action } => {
+            use cli::VariableActions::*;
+            let theme_name = get_current_theme()?;
+            match action {
+                Set {
+                    name,
+                    value,
+                    device,
+                    secret,
+                } => {
+                    CommandResult::AddRemove(set_variable(theme_name, name, value, device, secret))
+                }
+                Remove { name, device } => {
+                    CommandResult::AddRemove(remove_variable(theme_name, name, device))
+                }
+                List { device } => CommandResult::VariableList(list_variables(theme_name, device)),
+            }
+        }
+        Backup { action } => {
+            use cli::BackupActions::*;
+            match action {
+                List => CommandResult::BackupList(list_backups()),
+                Restore { id, path } => CommandResult::Restored(restore_backup(id, path)),
+                SetRetention {
+                    keep,
+                    max_age_days,
+                    no_max_age,
+                } => CommandResult::AddRemove(set_retention(keep, max_age_days, no_max_age)),
+            }
+        }
+        Diff {
+            config_name,
+            from,
+            to,
+        } => CommandResult::Diff(diff_config(config_name, from, to)),
+        Secret { action } => {
+            use cli::SecretActions::*;
+            match action {
+                Key => CommandResult::DependencyThemeList(public_key()),
+                AddRecipient { key } => {
+                    CommandResult::AddRemove(add_recipient(get_current_theme()?, key))
+                }
+            }
+        }
     };
-    result.print();
-
-    Ok(())
+    Ok(result)
 }
@@ -0,0 +1,176 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::os::unix::fs::MetadataExt;
+use std::process::{Command, Output};
+
+use crate::device::device_lineage;
+use crate::error::ConfigCliError;
+use crate::theme::{load_manifest, theme_chain};
+use crate::utils::{find_executable, ConfigResult};
+
+pub mod apk;
+pub mod apt;
+pub mod aur;
+pub mod brew;
+pub mod dnf;
+pub mod pacman;
+pub mod zypper;
+
+pub use apk::*;
+pub use apt::*;
+pub use aur::*;
+pub use brew::*;
+pub use dnf::*;
+pub use pacman::*;
+pub use zypper::*;
+
+/// A system package manager that dependencies can be queried, searched and installed with
+pub trait PackageManager {
+    fn name(&self) -> &'static str;
+    /// Returns the subset of the packages that is already installed, using a single query
+    fn query(&self, packages: &[String]) -> ConfigResult<Vec<String>>;
+    fn install(&self, packages: &[String]) -> ConfigResult<()>;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManagerKind {
+    Pacman,
+    Yay,
+    Paru,
+    Apt,
+    Dnf,
+    Zypper,
+    Apk,
+    Brew,
+}
+
+impl PackageManagerKind {
+    // Order in which package managers are tried when auto-detecting
+    const DETECTION_ORDER: [PackageManagerKind; 8] = [
+        PackageManagerKind::Paru,
+        PackageManagerKind::Yay,
+        PackageManagerKind::Pacman,
+        PackageManagerKind::Apt,
+        PackageManagerKind::Dnf,
+        PackageManagerKind::Zypper,
+        PackageManagerKind::Apk,
+        PackageManagerKind::Brew,
+    ];
+
+    fn executable(&self) -> &'static str {
+        use PackageManagerKind::*;
+        match self {
+            Pacman => "pacman",
+            Yay => "yay",
+            Paru => "paru",
+            Apt => "apt-get",
+            Dnf => "dnf",
+            Zypper => "zypper",
+            Apk => "apk",
+            Brew => "brew",
+        }
+    }
+
+    pub fn backend(&self) -> Box<dyn PackageManager> {
+        use PackageManagerKind::*;
+        match self {
+            Pacman => Box::new(self::Pacman),
+            Yay => Box::new(Aur::new("yay")),
+            Paru => Box::new(Aur::new("paru")),
+            Apt => Box::new(self::Apt),
+            Dnf => Box::new(self::Dnf),
+            Zypper => Box::new(self::Zypper),
+            Apk => Box::new(self::Apk),
+            Brew => Box::new(self::Brew),
+        }
+    }
+
+    /// Picks the first package manager that is installed on the system
+    pub fn detect() -> ConfigResult<Self> {
+        Self::DETECTION_ORDER
+            .into_iter()
+            .find(|kind| find_executable(kind.executable()).is_some())
+            .ok_or(ConfigCliError::NoPackageManagerFound)
+    }
+}
+
+/// Returns the package manager configured for the device in the theme or detects one
+pub fn get_package_manager(
+    theme: &str,
+    device: Option<&str>,
+) -> ConfigResult<Box<dyn PackageManager>> {
+    if let Some(device) = device {
+        let device_files = theme_chain(theme)?
+            .iter()
+            .rev()
+            .map(|x| Ok(load_manifest(x)?.devices))
+            .collect::<ConfigResult<Vec<_>>>()?;
+
+        // The most specific name of the lineage that has one wins, then the closest theme
+        for name in device_lineage(theme, device)?.iter().rev() {
+            for device_file in &device_files {
+                if let Some((_, kind)) = device_file.package_managers.iter().find(|x| &x.0 == name)
+                {
+                    return Ok(kind.backend());
+                }
+            }
+        }
+    }
+
+    Ok(PackageManagerKind::detect()?.backend())
+}
+
+// Runs a package manager command and captures its output
+fn run_captured(command: &mut Command) -> ConfigResult<Output> {
+    match command.output() {
+        Ok(output) => Ok(output),
+        Err(err) => Err(ConfigCliError::ShellInitError(err)),
+    }
+}
+
+// Runs a package manager command attached to the terminal so prompts are visible
+//
+// Its output goes to stderr, stdout only carries the result of the command, like a JSON document.
+fn run_interactive(command: &mut Command) -> ConfigResult<()> {
+    let status = match command.stdout(std::io::stderr()).status() {
+        Ok(status) => status,
+        Err(err) => return Err(ConfigCliError::ShellInitError(err)),
+    };
+
+    match status.success() {
+        true => Ok(()),
+        false => Err(ConfigCliError::PackageManagerError(status.to_string())),
+    }
+}
+
+fn stdout_lines(output: Output) -> ConfigResult<Vec<String>> {
+    match std::str::from_utf8(&output.stdout) {
+        Ok(s) => Ok(s.lines().map(|x| x.trim().to_owned()).collect()),
+        Err(err) => Err(ConfigCliError::StringConversionError(err)),
+    }
+}
+
+// Package managers print one installed package per line, starting with its name
+fn installed_packages(output: Output, packages: &[String]) -> ConfigResult<Vec<String>> {
+    Ok(stdout_lines(output)?
+        .into_iter()
+        .filter_map(|x| x.split_whitespace().next().map(|x| x.to_owned()))
+        .filter(|x| packages.contains(x))
+        .collect())
+}
+
+// Builds a command that runs as root, going through sudo if necessary
+fn privileged(program: &str) -> Command {
+    let is_root = std::fs::metadata("/proc/self")
+        .map(|meta| meta.uid() == 0)
+        .unwrap_or(false);
+
+    if is_root {
+        Command::new(program)
+    } else {
+        let mut command = Command::new("sudo");
+        command.arg(program);
+        command
+    }
+}
@@ -0,0 +1,21 @@
+use std::process::Command;
+
+use super::{installed_packages, privileged, run_captured, run_interactive, PackageManager};
+use crate::utils::ConfigResult;
+
+pub struct Apk;
+
+impl PackageManager for Apk {
+    fn name(&self) -> &'static str {
+        "apk"
+    }
+
+    fn query(&self, packages: &[String]) -> ConfigResult<Vec<String>> {
+        let output = run_captured(Command::new("apk").arg("info").arg("-e").args(packages))?;
+        installed_packages(output, packages)
+    }
+
+    fn install(&self, packages: &[String]) -> ConfigResult<()> {
+        run_interactive(privileged("apk").arg("add").args(packages))
+    }
+}
@@ -0,0 +1,35 @@
+use std::process::Command;
+
+use super::{privileged, run_captured, run_interactive, stdout_lines, PackageManager};
+use crate::utils::ConfigResult;
+
+pub struct Apt;
+
+impl PackageManager for Apt {
+    fn name(&self) -> &'static str {
+        "apt"
+    }
+
+    fn query(&self, packages: &[String]) -> ConfigResult<Vec<String>> {
+        // dpkg-query also lists packages that were removed but not purged
+        let lines = stdout_lines(run_captured(
+            Command::new("dpkg-query")
+                .arg("-W")
+                .arg("-f=${Package} ${db:Status-Status}\n")
+                .args(packages),
+        )?)?;
+        Ok(lines
+            .into_iter()
+            .filter_map(|x| {
+                x.split_once(' ')
+                    .filter(|(_, status)| *status == "installed")
+                    .map(|(name, _)| name.to_owned())
+            })
+            .filter(|x| packages.contains(x))
+            .collect())
+    }
+
+    fn install(&self, packages: &[String]) -> ConfigResult<()> {
+        run_interactive(privileged("apt-get").arg("install").args(packages))
+    }
+}
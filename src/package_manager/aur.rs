@@ -0,0 +1,35 @@
+use std::process::Command;
+
+use super::{installed_packages, run_captured, run_interactive, PackageManager};
+use crate::utils::ConfigResult;
+
+/// AUR helpers like yay and paru share the pacman command line and elevate on their own
+pub struct Aur {
+    helper: &'static str,
+}
+
+impl Aur {
+    pub fn new(helper: &'static str) -> Self {
+        Aur { helper }
+    }
+}
+
+impl PackageManager for Aur {
+    fn name(&self) -> &'static str {
+        self.helper
+    }
+
+    fn query(&self, packages: &[String]) -> ConfigResult<Vec<String>> {
+        let output = run_captured(Command::new(self.helper).arg("-Q").args(packages))?;
+        installed_packages(output, packages)
+    }
+
+    fn install(&self, packages: &[String]) -> ConfigResult<()> {
+        run_interactive(
+            Command::new(self.helper)
+                .arg("-S")
+                .arg("--needed")
+                .args(packages),
+        )
+    }
+}
@@ -0,0 +1,27 @@
+use std::process::Command;
+
+use super::{installed_packages, run_captured, run_interactive, PackageManager};
+use crate::utils::ConfigResult;
+
+/// Homebrew on Linux, which installs into its own prefix and must not be run as root
+pub struct Brew;
+
+impl PackageManager for Brew {
+    fn name(&self) -> &'static str {
+        "brew"
+    }
+
+    fn query(&self, packages: &[String]) -> ConfigResult<Vec<String>> {
+        let output = run_captured(
+            Command::new("brew")
+                .arg("list")
+                .arg("--versions")
+                .args(packages),
+        )?;
+        installed_packages(output, packages)
+    }
+
+    fn install(&self, packages: &[String]) -> ConfigResult<()> {
+        run_interactive(Command::new("brew").arg("install").args(packages))
+    }
+}
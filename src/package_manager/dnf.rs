@@ -0,0 +1,27 @@
+use std::process::Command;
+
+use super::{installed_packages, privileged, run_captured, run_interactive, PackageManager};
+use crate::utils::ConfigResult;
+
+pub struct Dnf;
+
+impl PackageManager for Dnf {
+    fn name(&self) -> &'static str {
+        "dnf"
+    }
+
+    fn query(&self, packages: &[String]) -> ConfigResult<Vec<String>> {
+        let output = run_captured(
+            Command::new("rpm")
+                .arg("-q")
+                .arg("--queryformat")
+                .arg("%{NAME}\n")
+                .args(packages),
+        )?;
+        installed_packages(output, packages)
+    }
+
+    fn install(&self, packages: &[String]) -> ConfigResult<()> {
+        run_interactive(privileged("dnf").arg("install").args(packages))
+    }
+}
@@ -0,0 +1,26 @@
+use std::process::Command;
+
+use super::{installed_packages, privileged, run_captured, run_interactive, PackageManager};
+use crate::utils::ConfigResult;
+
+pub struct Pacman;
+
+impl PackageManager for Pacman {
+    fn name(&self) -> &'static str {
+        "pacman"
+    }
+
+    fn query(&self, packages: &[String]) -> ConfigResult<Vec<String>> {
+        let output = run_captured(Command::new("pacman").arg("-Q").args(packages))?;
+        installed_packages(output, packages)
+    }
+
+    fn install(&self, packages: &[String]) -> ConfigResult<()> {
+        run_interactive(
+            privileged("pacman")
+                .arg("-S")
+                .arg("--needed")
+                .args(packages),
+        )
+    }
+}
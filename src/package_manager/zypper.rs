@@ -0,0 +1,27 @@
+use std::process::Command;
+
+use super::{installed_packages, privileged, run_captured, run_interactive, PackageManager};
+use crate::utils::ConfigResult;
+
+pub struct Zypper;
+
+impl PackageManager for Zypper {
+    fn name(&self) -> &'static str {
+        "zypper"
+    }
+
+    fn query(&self, packages: &[String]) -> ConfigResult<Vec<String>> {
+        let output = run_captured(
+            Command::new("rpm")
+                .arg("-q")
+                .arg("--queryformat")
+                .arg("%{NAME}\n")
+                .args(packages),
+        )?;
+        installed_packages(output, packages)
+    }
+
+    fn install(&self, packages: &[String]) -> ConfigResult<()> {
+        run_interactive(privileged("zypper").arg("install").args(packages))
+    }
+}
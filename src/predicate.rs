@@ -0,0 +1,377 @@
+use crate::error::ConfigCliError;
+use crate::facts::{builtin_facts, gpu_vendors, session_type};
+use crate::utils::{find_executable, glob_matches, ConfigResult};
+
+/// A condition like `has(sway) && env(XDG_SESSION_TYPE) == 'wayland'`
+///
+/// Terms are calls, quoted strings or bare words, compared with `==`, `!=` or `~=` for a glob
+/// on the right and combined with `!`, `&&`, `||` and parentheses. A term on its own holds if
+/// it has a value that is neither empty nor `false`.
+///
+/// - `has(name)` is `true` if the executable is on `PATH`
+/// - `env(NAME)` is the environment variable, if it is set
+/// - `session()` is `wayland`, `x11` or `tty`
+/// - `gpu()` is the vendors of the graphics cards, like `intel nvidia`
+/// - `fact(name)` is a built-in fact, like `fact(os)` or `fact(hostname)`
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Term, Operator, Term),
+    Term(Term),
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    Call(String, String),
+    Literal(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Glob,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Not,
+    And,
+    Or,
+    Equal,
+    NotEqual,
+    Glob,
+    Word(String),
+    Quoted(String),
+}
+
+/// Whether a predicate holds, with the values of the calls it looked at
+#[derive(Debug)]
+pub struct Evaluation {
+    pub holds: bool,
+    /// Like `has(sway) = false`, in the order they were evaluated
+    pub observed: Vec<String>,
+}
+
+impl Predicate {
+    pub fn parse(source: &str) -> ConfigResult<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            source,
+            tokens,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        if parser.position < parser.tokens.len() {
+            return Err(parser.error("unexpected token"));
+        }
+        Ok(Predicate { expr })
+    }
+
+    pub fn evaluate(&self) -> ConfigResult<Evaluation> {
+        let mut observed = vec![];
+        let holds = evaluate(&self.expr, &mut observed)?;
+        Ok(Evaluation { holds, observed })
+    }
+}
+
+fn tokenize(source: &str) -> ConfigResult<Vec<Token>> {
+    let invalid =
+        |reason: &str| ConfigCliError::InvalidPredicate(format!("{}: {}", reason, source));
+
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+    while let Some(char) = chars.next() {
+        let token = match char {
+            ' ' | '\t' => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '!' if chars.next_if_eq(&'=').is_some() => Token::NotEqual,
+            '!' => Token::Not,
+            '&' if chars.next_if_eq(&'&').is_some() => Token::And,
+            '|' if chars.next_if_eq(&'|').is_some() => Token::Or,
+            '=' if chars.next_if_eq(&'=').is_some() => Token::Equal,
+            '~' if chars.next_if_eq(&'=').is_some() => Token::Glob,
+            '\'' | '"' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == char => break,
+                        Some(next) => quoted.push(next),
+                        None => return Err(invalid("unterminated string")),
+                    }
+                }
+                Token::Quoted(quoted)
+            }
+            _ if is_word_char(char) => {
+                let mut word = char.to_string();
+                while let Some(next) = chars.next_if(|x| is_word_char(*x)) {
+                    word.push(next);
+                }
+                Token::Word(word)
+            }
+            _ => return Err(invalid(&format!("unexpected character `{}`", char))),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn is_word_char(char: char) -> bool {
+    char.is_alphanumeric() || matches!(char, '_' | '-' | '.' | '*' | '/')
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &str) -> ConfigCliError {
+        ConfigCliError::InvalidPredicate(format!("{}: {}", reason, self.source))
+    }
+
+    fn next_if(&mut self, token: &Token) -> bool {
+        let matches = self.tokens.get(self.position) == Some(token);
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> ConfigResult<Expr> {
+        let mut expr = self.and()?;
+        while self.next_if(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> ConfigResult<Expr> {
+        let mut expr = self.unary()?;
+        while self.next_if(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> ConfigResult<Expr> {
+        if self.next_if(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.next_if(&Token::Open) {
+            let expr = self.or()?;
+            if !self.next_if(&Token::Close) {
+                return Err(self.error("missing `)`"));
+            }
+            return Ok(expr);
+        }
+
+        let term = self.term()?;
+        let operator = match self.tokens.get(self.position) {
+            Some(Token::Equal) => Operator::Equal,
+            Some(Token::NotEqual) => Operator::NotEqual,
+            Some(Token::Glob) => Operator::Glob,
+            _ => return Ok(Expr::Term(term)),
+        };
+        self.position += 1;
+        Ok(Expr::Compare(term, operator, self.term()?))
+    }
+
+    fn term(&mut self) -> ConfigResult<Term> {
+        let name = match self.next() {
+            Some(Token::Quoted(value)) => return Ok(Term::Literal(value)),
+            Some(Token::Word(name)) => name,
+            _ => return Err(self.error("expected a call or a value")),
+        };
+        if !self.next_if(&Token::Open) {
+            return Ok(Term::Literal(name));
+        }
+
+        let argument = match self.tokens.get(self.position) {
+            Some(Token::Word(value) | Token::Quoted(value)) => {
+                let value = value.clone();
+                self.position += 1;
+                value
+            }
+            _ => String::new(),
+        };
+        if !self.next_if(&Token::Close) {
+            return Err(self.error("missing `)`"));
+        }
+
+        let takes_argument = match name.as_str() {
+            "has" | "env" | "fact" => true,
+            "session" | "gpu" => false,
+            _ => return Err(self.error(&format!("unknown function `{}`", name))),
+        };
+        if takes_argument == argument.is_empty() {
+            return Err(self.error(&format!("wrong arguments for `{}`", name)));
+        }
+        Ok(Term::Call(name, argument))
+    }
+}
+
+fn evaluate(expr: &Expr, observed: &mut Vec<String>) -> ConfigResult<bool> {
+    Ok(match expr {
+        Expr::Or(left, right) => evaluate(left, observed)? || evaluate(right, observed)?,
+        Expr::And(left, right) => evaluate(left, observed)? && evaluate(right, observed)?,
+        Expr::Not(expr) => !evaluate(expr, observed)?,
+        Expr::Term(term) => term
+            .value(observed)
+            .is_some_and(|x| !x.is_empty() && x != "false"),
+        Expr::Compare(left, operator, right) => {
+            let (left, right) = (left.value(observed), right.value(observed));
+            match operator {
+                Operator::Equal => left.is_some() && left == right,
+                Operator::NotEqual => left.is_none() || left != right,
+                Operator::Glob => match (left, right) {
+                    (Some(value), Some(pattern)) => glob_matches(&pattern, &value)?,
+                    _ => false,
+                },
+            }
+        }
+    })
+}
+
+impl Term {
+    fn value(&self, observed: &mut Vec<String>) -> Option<String> {
+        let (name, argument) = match self {
+            Term::Literal(value) => return Some(value.clone()),
+            Term::Call(name, argument) => (name, argument),
+        };
+        let value = match name.as_str() {
+            "has" => Some(find_executable(argument).is_some().to_string()),
+            "env" => std::env::var(argument).ok(),
+            "session" => Some(session_type()),
+            "gpu" => Some(gpu_vendors()),
+            "fact" => builtin_facts().remove(argument),
+            _ => None,
+        };
+
+        let shown = match &value {
+            Some(value) => format!("{}({}) = '{}'", name, argument, value),
+            None => format!("{}({}) is unset", name, argument),
+        };
+        if !observed.contains(&shown) {
+            observed.push(shown);
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holds(source: &str) -> bool {
+        Predicate::parse(source).unwrap().evaluate().unwrap().holds
+    }
+
+    fn parse_error(source: &str) -> String {
+        match Predicate::parse(source) {
+            Ok(_) => panic!("`{}` parsed", source),
+            Err(ConfigCliError::InvalidPredicate(reason)) => reason,
+            Err(err) => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn compares_literals() {
+        assert!(holds("'wayland' == wayland"));
+        assert!(holds("\"a\" != 'b'"));
+        assert!(!holds("a == b"));
+        assert!(holds("wayland ~= 'way*'"));
+        assert!(!holds("x11 ~= 'way*'"));
+    }
+
+    #[test]
+    fn bare_terms_hold_unless_empty_or_false() {
+        assert!(holds("yes"));
+        assert!(!holds("false"));
+        assert!(!holds("''"));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert!(holds("a == a || a == b && a == c"));
+        assert!(!holds("(a == a || a == b) && a == c"));
+        assert!(holds("!(a == b) && !false"));
+        assert!(holds("!!yes"));
+    }
+
+    #[test]
+    fn reads_environment_variables() {
+        std::env::set_var("CONFIG_MANAGER_PREDICATE_TEST", "sway");
+        assert!(holds("env(CONFIG_MANAGER_PREDICATE_TEST) == sway"));
+        assert!(holds("env(CONFIG_MANAGER_PREDICATE_TEST)"));
+        assert!(!holds("env(CONFIG_MANAGER_PREDICATE_UNSET)"));
+        assert!(!holds("env(CONFIG_MANAGER_PREDICATE_UNSET) == ''"));
+        assert!(holds("env(CONFIG_MANAGER_PREDICATE_UNSET) != sway"));
+    }
+
+    #[test]
+    fn records_observed_values_once() {
+        std::env::set_var("CONFIG_MANAGER_PREDICATE_OBSERVED", "x11");
+        let evaluation = Predicate::parse(
+            "env(CONFIG_MANAGER_PREDICATE_OBSERVED) == wayland || env(CONFIG_MANAGER_PREDICATE_OBSERVED) == x11",
+        )
+        .unwrap()
+        .evaluate()
+        .unwrap();
+        assert!(evaluation.holds);
+        assert_eq!(
+            evaluation.observed,
+            vec!["env(CONFIG_MANAGER_PREDICATE_OBSERVED) = 'x11'"]
+        );
+    }
+
+    #[test]
+    fn short_circuits() {
+        let evaluation = Predicate::parse("yes || env(CONFIG_MANAGER_PREDICATE_SKIPPED)")
+            .unwrap()
+            .evaluate()
+            .unwrap();
+        assert!(evaluation.holds);
+        assert!(evaluation.observed.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_conditions() {
+        assert!(parse_error("").starts_with("expected a call or a value"));
+        assert!(parse_error("(a == a").starts_with("missing `)`"));
+        assert!(parse_error("a == a)").starts_with("unexpected token"));
+        assert!(parse_error("'open").starts_with("unterminated string"));
+        assert!(parse_error("a = b").starts_with("unexpected character `=`"));
+        assert!(parse_error("a & b").starts_with("unexpected character `&`"));
+        assert!(parse_error("which(sway)").starts_with("unknown function `which`"));
+        assert!(parse_error("has()").starts_with("wrong arguments for `has`"));
+        assert!(parse_error("session(tty)").starts_with("wrong arguments for `session`"));
+        assert!(parse_error("a && || b").starts_with("expected a call or a value"));
+    }
+
+    #[test]
+    fn invalid_globs_fail_on_evaluation() {
+        let predicate = Predicate::parse("a ~= '[a'").unwrap();
+        assert!(matches!(
+            predicate.evaluate(),
+            Err(ConfigCliError::InvalidPattern(_))
+        ));
+    }
+}
@@ -1,29 +1,389 @@
+use colored::Colorize;
+use serde::Serialize;
+use std::process::ExitCode;
+
+use crate::cli::OutputFormat;
+use crate::dependency::Installation;
+use crate::device::{DeviceDetection, DeviceInfo, RuleCheck};
+use crate::diff::colorize;
+use crate::error::ConfigCliError;
+use crate::hook::HookRun;
+use crate::status::{EntryState, StatusReport};
+use crate::theme::{EffectiveConfig, FileAction, SkippedConfig, ThemePlan};
+use crate::utils::ConfigResult;
 use crate::CommandResult;
 
 impl CommandResult {
-    pub fn print(&self) {
+    /// Prints the result to stdout and the error to stderr, returning the exit code
+    pub fn print(&self, format: OutputFormat) -> ExitCode {
         use CommandResult::*;
         match self {
-            AddRemove(result) => match result {
-                Ok(_) => return (),
-                Err(err) => println!("{}", err.to_string()),
-            },
-            DependencyThemeList(result) => match result {
-                Ok(ok) => {
-                    for item in ok {
-                        println!("{}", item);
-                    }
+            AddRemove(result) => emit(result, format, |_| ()),
+            DependencyThemeList(result) => emit(result, format, |ok| {
+                for item in ok {
+                    println!("{}", item);
+                }
+            }),
+            ConfigList(result) => emit(result, format, |ok| print_config_list(ok)),
+            DeviceConfigList(result) => emit(result, format, |ok| {
+                let mut order = vec!["theme".to_owned()];
+                order.extend(ok.lineage.iter().cloned());
+                println!(
+                    "Resolving {} through {}",
+                    ok.device.bold(),
+                    order.join(" < ")
+                );
+                print_config_list(&ok.configs);
+            }),
+            ThemePlan(result) => emit(result, format, print_theme_plan),
+            Status(result) => emit(result, format, print_status),
+            VariableList(result) => emit(result, format, |ok| {
+                for (name, value) in ok {
+                    println!("{} = {}", name, value);
+                }
+            }),
+            Hooks(result) => emit(result, format, print_hook_runs),
+            ThemeSwitch(result) => emit(result, format, |ok| {
+                if let Some(installation) = &ok.installation {
+                    print_installation(installation);
+                }
+                print_hook_runs(&ok.hooks);
+            }),
+            Installation(result) => emit(result, format, |ok| {
+                if let Some(installation) = ok {
+                    print_installation(installation);
+                }
+            }),
+            Diff(result) => emit(result, format, |ok| {
+                if ok.is_empty() {
+                    println!("No differences");
                 }
-                Err(err) => println!("{}", err.to_string()),
-            },
-            ConfigList(result) => match result {
-                Ok(ok) => {
-                    for item in ok {
-                        println!("{}", item.name);
+                for file in ok {
+                    print!("{}", colorize(&file.diff));
+                }
+            }),
+            BackupList(result) => emit(result, format, |ok| {
+                for backup in ok {
+                    println!(
+                        "{} {} ({} file(s))",
+                        backup.id.bold(),
+                        backup.operation,
+                        backup.files.len()
+                    );
+                    for file in &backup.files {
+                        println!("  {}", file.display());
                     }
                 }
-                Err(err) => println!("{}", err.to_string()),
-            },
+            }),
+            Restored(result) => emit(result, format, |ok| {
+                if let Some(id) = ok {
+                    println!("The replaced files were backed up as {}", id);
+                }
+            }),
+            DeviceDetection(result) => emit(result, format, print_device_detection),
+            DeviceInfo(result) => emit(result, format, print_device_info),
+            DeviceRemoved(result) => emit(result, format, |ok| {
+                for config in ok {
+                    println!(
+                        "Removed {}, {} is left standalone",
+                        config.name.bold(),
+                        config.symlink.display()
+                    );
+                }
+            }),
+            Synced(result) => emit(result, format, |ok| {
+                if ok.is_empty() {
+                    println!("Nothing to sync");
+                }
+                for file in ok {
+                    println!("{} {}", file.config.bold(), file.path.display());
+                    print!("{}", colorize(&file.diff));
+                }
+            }),
+        }
+    }
+}
+
+fn emit<T: Serialize>(
+    result: &ConfigResult<T>,
+    format: OutputFormat,
+    print_text: impl Fn(&T),
+) -> ExitCode {
+    match (result, format) {
+        (Ok(ok), OutputFormat::Text) => {
+            print_text(ok);
+            ExitCode::SUCCESS
+        }
+        (Ok(ok), OutputFormat::Json) => {
+            let document = serde_json::json!({ "ok": true, "data": ok });
+            println!("{}", document);
+            ExitCode::SUCCESS
+        }
+        (Err(err), OutputFormat::Text) => {
+            eprintln!("{}", err);
+            err.category().exit_code()
+        }
+        (Err(err), OutputFormat::Json) => {
+            eprintln!("{}", error_document(err));
+            err.category().exit_code()
+        }
+    }
+}
+
+fn error_document(err: &ConfigCliError) -> serde_json::Value {
+    serde_json::json!({
+        "ok": false,
+        "error": {
+            "kind": err.kind(),
+            "category": err.category(),
+            "context": err.contexts(),
+            "message": err.to_string(),
+        }
+    })
+}
+
+fn print_config_list(configs: &[EffectiveConfig]) {
+    for item in configs {
+        match &item.device {
+            Some(device) => println!(
+                "{:<24} {} ({})",
+                item.config.name,
+                item.layer.dimmed(),
+                device
+            ),
+            None => println!("{:<24} {}", item.config.name, item.layer.dimmed()),
+        }
+    }
+}
+
+fn print_device_detection(detection: &DeviceDetection) {
+    match &detection.device {
+        Some(device) => println!("Detected device {}", device.bold()),
+        None => println!("No device matches this machine"),
+    }
+    for profile in &detection.profiles {
+        let selected = detection.device.as_ref() == Some(&profile.device);
+        let state = match (selected, profile.matched) {
+            (true, _) => "selected".green(),
+            (false, true) => "matches".yellow(),
+            (false, false) => "no match".dimmed(),
+        };
+        println!("  {} ({}) {}", profile.device, profile.theme, state);
+        for check in &profile.checks {
+            print_rule_check(check, 4);
+        }
+    }
+}
+
+fn print_rule_check(check: &RuleCheck, indent: usize) {
+    let mark = match check.matched {
+        true => "ok".green(),
+        false => "mismatch".red(),
+    };
+    println!(
+        "{:indent$}{:<10} {} is {}, expected {}",
+        "", mark, check.rule, check.actual, check.expected
+    );
+}
+
+fn print_device_info(info: &DeviceInfo) {
+    let registered = match info.registered {
+        true => "registered",
+        false => "only bound to",
+    };
+    println!("Device {} ({})", info.name.bold(), registered.dimmed());
+    if let Some(parent) = &info.parent {
+        println!("  inherits from {}", parent);
+    }
+    if !info.groups.is_empty() {
+        println!("  member of {}", info.groups.join(", "));
+    }
+    let mut order = vec!["theme".to_owned()];
+    order.extend(info.lineage.iter().cloned());
+    println!("  resolved through {}", order.join(" < "));
+
+    println!("Configs");
+    for item in &info.configs {
+        let bound = match &item.device {
+            Some(device) => format!("{} ({})", item.layer, device),
+            None => item.layer.clone(),
+        };
+        println!("  {:<24} {}", item.config.name, bound.dimmed());
+    }
+    println!("Dependencies");
+    for dependency in &info.dependencies {
+        println!("  {}", dependency);
+    }
+    println!("Variables");
+    for (name, value) in &info.variables {
+        println!("  {} = {}", name, value);
+    }
+    match info.detected {
+        true => println!("Facts, this machine is {}", info.name),
+        false => println!("Facts"),
+    }
+    for check in &info.facts {
+        print_rule_check(check, 2);
+    }
+}
+
+fn print_theme_plan(plan: &ThemePlan) {
+    match (&plan.target_root, &plan.previous_theme) {
+        (Some(root), _) => println!(
+            "Deploying theme {} below {}",
+            plan.theme.bold(),
+            root.display()
+        ),
+        (None, Some(previous)) => {
+            println!("Switching theme {} -> {}", previous, plan.theme.bold())
+        }
+        (None, None) => println!("Switching to theme {}", plan.theme.bold()),
+    }
+    if let Some(device) = &plan.device {
+        println!("Device {}", device);
+    }
+
+    for file in &plan.files {
+        let action = match file.action {
+            FileAction::Create => "create".green(),
+            FileAction::Overwrite => "overwrite".yellow(),
+            FileAction::ReplaceSymlink => "replace symlink".yellow(),
+        };
+        let conflict = match file.conflict {
+            true => " (conflict)".red().to_string(),
+            false => String::new(),
+        };
+        println!(
+            "  {:<16} {} <- {} [{}]{}",
+            action,
+            file.target.display(),
+            file.source.display(),
+            file.config,
+            conflict
+        );
+    }
+    print_skipped(&plan.skipped, 16);
+
+    if !plan.dependencies.missing.is_empty() {
+        println!("Dependencies to install:");
+        for dependency in &plan.dependencies.missing {
+            println!("  {}", dependency.0.yellow());
+        }
+    }
+
+    let conflicts = plan.conflicts().count();
+    if conflicts > 0 {
+        match plan.force {
+            true => println!("{} conflict(s) will be overridden by --force", conflicts),
+            false => println!(
+                "{}",
+                format!("{} conflict(s), rerun with --force to override", conflicts).red()
+            ),
+        }
+    }
+}
+
+fn print_skipped(skipped: &[SkippedConfig], width: usize) {
+    for config in skipped {
+        println!(
+            "  {:<width$} {} [{}]: {}",
+            "skip".dimmed(),
+            config.config,
+            config.when,
+            config.reason
+        );
+    }
+}
+
+fn print_status(report: &StatusReport) {
+    match &report.device {
+        Some(device) => println!("Theme {} on device {}", report.theme.bold(), device),
+        None => println!("Theme {}", report.theme.bold()),
+    }
+
+    for config in &report.configs {
+        let state = match &config.state {
+            EntryState::InSync => "in sync".green(),
+            EntryState::Modified => "modified".yellow(),
+            EntryState::Missing => "missing".red(),
+            EntryState::ReplacedByFile => "replaced".red(),
+            EntryState::WrongSymlinkTarget { .. } => "wrong link".red(),
+        };
+        print!(
+            "  {:<12} {} ({})",
+            state,
+            config.name,
+            config.target.display()
+        );
+        if let EntryState::WrongSymlinkTarget { link } = &config.state {
+            print!(" -> {}", link.display());
+        }
+        println!();
+        for drift in &config.attribute_drift {
+            println!(
+                "  {:<12} {} is {}, expected {}",
+                "permissions".red(),
+                drift.path.display(),
+                drift.actual,
+                drift.expected
+            );
+        }
+    }
+    print_skipped(&report.skipped, 12);
+
+    if !report.git_changes.is_empty() {
+        println!("Uncommitted changes:");
+        for change in &report.git_changes {
+            println!("  {}", change.yellow());
+        }
+    }
+
+    if !report.missing_dependencies.is_empty() {
+        println!("Missing dependencies:");
+        for dependency in &report.missing_dependencies {
+            println!("  {}", dependency.red());
+        }
+    }
+}
+
+fn print_installation(installation: &Installation) {
+    let plan = &installation.plan;
+    if plan.missing.is_empty() {
+        println!("{}", "All dependencies are already installed".green());
+        return;
+    }
+
+    println!(
+        "{} {} {}",
+        "Installed".bold(),
+        plan.missing.len(),
+        format!("package(s) with {}:", installation.package_manager).bold()
+    );
+    for dependency in &plan.missing {
+        println!("  {}", dependency.0.yellow());
+    }
+    if !plan.installed.is_empty() {
+        println!("{} already installed", plan.installed.len());
+    }
+}
+
+fn print_hook_runs(runs: &Vec<HookRun>) {
+    for run in runs {
+        let state = match (run.success, run.exit_code) {
+            (true, _) => "ok".green(),
+            (false, Some(code)) => format!("exit {}", code).yellow(),
+            (false, None) => "killed".yellow(),
+        };
+        let owner = run.config.as_ref().unwrap_or(&run.theme);
+        println!(
+            "{:<8} {} {} [{}]",
+            state,
+            run.stage.name(),
+            run.command,
+            owner
+        );
+        for line in run.stdout.lines().chain(run.stderr.lines()) {
+            println!("    {}", line.dimmed());
         }
     }
 }
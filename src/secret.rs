@@ -0,0 +1,175 @@
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::secrecy::ExposeSecret;
+use age::x25519::{Identity, Recipient};
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::ConfigCliError;
+use crate::theme::layered_configs;
+use crate::utils::{get_base_dir, get_machine_state_dir, ConfigResult};
+use crate::variable::read_variable_file;
+
+pub mod add_recipient;
+pub mod check;
+pub mod key;
+
+pub use add_recipient::*;
+pub use check::*;
+pub use key::*;
+
+/// Loads the private key of this machine, generating it on first use
+pub fn load_identity() -> ConfigResult<Identity> {
+    let path = get_machine_state_dir()?.join("identity.txt");
+
+    if !path.exists() {
+        let identity = Identity::generate();
+        let content = identity.to_string().expose_secret().clone() + "\n";
+        match std::fs::write(&path, content) {
+            Ok(_) => (),
+            Err(err) => return Err(ConfigCliError::FsWriteError(err).at(&path)),
+        }
+        // Only the owner may read the private key
+        match std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)) {
+            Ok(_) => (),
+            Err(err) => return Err(ConfigCliError::FsWriteError(err).at(&path)),
+        }
+        return Ok(identity);
+    }
+
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) => return Err(ConfigCliError::FsReadError(err).at(&path)),
+    };
+    Identity::from_str(content.trim())
+        .map_err(|err| ConfigCliError::DecryptionError(err.to_string()).at(&path))
+}
+
+/// This machine's public key and every recipient listed in `recipients.txt` of the theme
+fn recipients(theme: &str) -> ConfigResult<Vec<Recipient>> {
+    let mut recipients = vec![load_identity()?.to_public()];
+
+    let path = get_base_dir()? + theme + "/recipients.txt";
+    if !Path::new(&path).exists() {
+        return Ok(recipients);
+    }
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) => return Err(ConfigCliError::FsReadError(err).at(&path)),
+    };
+    for line in content.lines().map(|x| x.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let recipient = Recipient::from_str(line)
+            .map_err(|err| ConfigCliError::EncryptionError(format!("{}: {}", line, err)))?;
+        if !recipients.contains(&recipient) {
+            recipients.push(recipient);
+        }
+    }
+    Ok(recipients)
+}
+
+/// Encrypts the plaintext to every recipient of the theme as ASCII armored age
+pub fn encrypt(theme: &str, plaintext: &[u8]) -> ConfigResult<Vec<u8>> {
+    let recipients = recipients(theme)?
+        .into_iter()
+        .map(|x| Box::new(x) as Box<dyn age::Recipient + Send>)
+        .collect();
+    let encryptor = age::Encryptor::with_recipients(recipients)
+        .ok_or(ConfigCliError::EncryptionError("No recipients".to_owned()))?;
+
+    let mut ciphertext = vec![];
+    let result = ArmoredWriter::wrap_output(&mut ciphertext, Format::AsciiArmor)
+        .map_err(|err| err.to_string())
+        .and_then(|armor| encryptor.wrap_output(armor).map_err(|err| err.to_string()))
+        .and_then(|mut writer| {
+            writer.write_all(plaintext).map_err(|err| err.to_string())?;
+            writer.finish().map_err(|err| err.to_string())
+        })
+        .and_then(|armor| armor.finish().map_err(|err| err.to_string()));
+
+    match result {
+        Ok(_) => Ok(ciphertext),
+        Err(err) => Err(ConfigCliError::EncryptionError(err)),
+    }
+}
+
+pub fn decrypt(ciphertext: &[u8]) -> ConfigResult<Vec<u8>> {
+    let identity = load_identity()?;
+
+    let decryptor = match age::Decryptor::new(ArmoredReader::new(ciphertext)) {
+        Ok(age::Decryptor::Recipients(decryptor)) => decryptor,
+        Ok(age::Decryptor::Passphrase(_)) => {
+            return Err(ConfigCliError::DecryptionError(
+                "Passphrase encrypted secrets are not supported".to_owned(),
+            ))
+        }
+        Err(err) => return Err(ConfigCliError::DecryptionError(err.to_string())),
+    };
+
+    let mut plaintext = vec![];
+    let result = decryptor
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .map_err(|err| err.to_string())
+        .and_then(|mut reader| {
+            reader
+                .read_to_end(&mut plaintext)
+                .map_err(|err| err.to_string())
+        });
+
+    match result {
+        Ok(_) => Ok(plaintext),
+        Err(err) => Err(ConfigCliError::DecryptionError(err)),
+    }
+}
+
+pub fn decrypt_file(path: &Path) -> ConfigResult<Vec<u8>> {
+    match std::fs::read(path) {
+        Ok(ciphertext) => decrypt(&ciphertext).map_err(|err| err.at(path)),
+        Err(err) => Err(ConfigCliError::FsReadError(err).at(path)),
+    }
+}
+
+/// Decrypts the secret, `None` if it is only encrypted to other recipients than this machine
+pub fn decrypt_if_recipient(ciphertext: &[u8]) -> ConfigResult<Option<Vec<u8>>> {
+    match decrypt(ciphertext) {
+        Ok(plaintext) => Ok(Some(plaintext)),
+        Err(ConfigCliError::DecryptionError(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Decrypted content of every secret config and secret variable of the theme and its ancestors
+///
+/// Secrets this machine is not a recipient of are left out.
+pub fn theme_secrets(theme: &str) -> ConfigResult<Vec<Vec<u8>>> {
+    let mut secrets = vec![];
+
+    for config in layered_configs(theme)? {
+        if !config.config.secret {
+            continue;
+        }
+        let path = &config.config.conf_location;
+        let ciphertext = match std::fs::read(path) {
+            Ok(ciphertext) => ciphertext,
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(path)),
+        };
+        secrets.extend(decrypt_if_recipient(&ciphertext).map_err(|err| err.at(path))?);
+    }
+
+    for layer in crate::theme::theme_chain(theme)? {
+        let variable_file = read_variable_file(&layer)?;
+        let ciphertexts = variable_file.secrets.values().chain(
+            variable_file
+                .device_secrets
+                .iter()
+                .flat_map(|x| x.1.values()),
+        );
+        for ciphertext in ciphertexts {
+            secrets.extend(decrypt_if_recipient(ciphertext.as_bytes())?);
+        }
+    }
+    Ok(secrets)
+}
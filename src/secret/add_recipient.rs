@@ -0,0 +1,28 @@
+use age::x25519::Recipient;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::error::ConfigCliError;
+use crate::utils::{get_base_dir, ConfigResult};
+
+/// Adds a public key that future secrets of the theme are encrypted to
+pub fn add_recipient(theme: String, key: String) -> ConfigResult<()> {
+    if let Err(err) = Recipient::from_str(&key) {
+        return Err(ConfigCliError::EncryptionError(format!("{}: {}", key, err)));
+    }
+
+    let path = get_base_dir()? + &theme + "/recipients.txt";
+    let mut file = match std::fs::File::options()
+        .create(true)
+        .append(true)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(err) => return Err(ConfigCliError::FileCreationError(err).at(&path)),
+    };
+
+    match writeln!(file, "{}", key) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ConfigCliError::FsWriteError(err).at(&path)),
+    }
+}
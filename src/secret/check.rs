@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use super::theme_secrets;
+use crate::error::ConfigCliError;
+use crate::utils::{get_base_dir, ConfigResult};
+
+// Shorter secrets would match unrelated content all over the theme
+const MIN_SECRET_LENGTH: usize = 4;
+
+/// Fails if the plaintext of any secret shows up in a file of the theme directory
+pub fn check_for_leaks(theme: &str) -> ConfigResult<()> {
+    let secrets: Vec<Vec<u8>> = theme_secrets(theme)?
+        .into_iter()
+        .map(|x| x.trim_ascii().to_vec())
+        .filter(|x| x.len() >= MIN_SECRET_LENGTH)
+        .collect();
+
+    if secrets.is_empty() {
+        return Ok(());
+    }
+
+    let theme_path = get_base_dir()? + theme;
+    check_dir(Path::new(&theme_path), Path::new(&theme_path), &secrets)
+}
+
+fn check_dir(root: &Path, dir: &Path, secrets: &[Vec<u8>]) -> ConfigResult<()> {
+    let read_dir = match std::fs::read_dir(dir) {
+        Ok(dir) => dir,
+        Err(err) => return Err(ConfigCliError::FsReadError(err).at(dir)),
+    };
+
+    for entry in read_dir {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(dir)),
+        };
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        if file_name == ".git" || file_name.ends_with(".age") || path.is_symlink() {
+            continue;
+        }
+        if path.is_dir() {
+            check_dir(root, &path, secrets)?;
+            continue;
+        }
+
+        let content = match std::fs::read(&path) {
+            Ok(content) => content,
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(&path)),
+        };
+        let leaked = secrets.iter().any(|secret| {
+            content
+                .windows(secret.len())
+                .any(|window| window == secret.as_slice())
+        });
+        if leaked {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            return Err(ConfigCliError::SecretLeak(
+                relative.to_string_lossy().to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
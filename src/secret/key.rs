@@ -0,0 +1,7 @@
+use super::load_identity;
+use crate::utils::ConfigResult;
+
+/// Returns the public key others add to their `recipients.txt` to share secrets with this machine
+pub fn public_key() -> ConfigResult<Vec<String>> {
+    Ok(vec![load_identity()?.to_public().to_string()])
+}
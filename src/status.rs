@@ -0,0 +1,239 @@
+use git2::{Repository, Status, StatusOptions};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{attribute_drift, AttributeDrift, Config, DeployMode};
+use crate::dependency::{collect_dependencies, InstallPlan};
+use crate::device::resolve_device;
+use crate::diff::collect_files;
+use crate::error::ConfigCliError;
+use crate::package_manager::get_package_manager;
+use crate::theme::{applied_configs, SkippedConfig};
+use crate::try_git;
+use crate::utils::{get_base_dir, get_current_theme, hash_path, resolve_link, ConfigResult};
+use crate::variable::resolve_variables;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum EntryState {
+    InSync,
+    /// The deployed copy differs from the theme store
+    Modified,
+    Missing,
+    /// The target is no longer deployed the way its mode says, like a link replaced by a file
+    ReplacedByFile,
+    WrongSymlinkTarget {
+        link: PathBuf,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigStatus {
+    pub name: String,
+    pub target: PathBuf,
+    #[serde(flatten)]
+    pub state: EntryState,
+    /// Files whose mode or owner is not the one the config recorded
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attribute_drift: Vec<AttributeDrift>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    pub theme: String,
+    pub device: Option<String>,
+    pub configs: Vec<ConfigStatus>,
+    /// Configs left out because their condition does not hold
+    pub skipped: Vec<SkippedConfig>,
+    /// Uncommitted changes in the theme directory in `git status --short` form
+    pub git_changes: Vec<String>,
+    pub missing_dependencies: Vec<String>,
+}
+
+pub fn status(device: Option<String>, target_root: Option<PathBuf>) -> ConfigResult<StatusReport> {
+    let theme = get_current_theme()?;
+    // Like `theme use`, the facts and packages of this machine say nothing about a target root
+    let device = match target_root {
+        Some(_) => device,
+        None => resolve_device(&theme, device)?,
+    };
+
+    let variables = resolve_variables(&theme, device.as_deref())?;
+    let (configs, skipped) = applied_configs(&theme, device.as_deref())?;
+    let configs = configs
+        .iter()
+        .map(|x| config_status(x, &variables, target_root.as_deref()))
+        .collect::<ConfigResult<Vec<ConfigStatus>>>()?;
+
+    let dependencies = match target_root {
+        Some(_) => vec![],
+        None => collect_dependencies(&theme, device.as_deref())?,
+    };
+    let missing_dependencies = match dependencies.is_empty() {
+        true => vec![],
+        false => {
+            let package_manager = get_package_manager(&theme, device.as_deref())?;
+            InstallPlan::from_dependencies(dependencies, package_manager.as_ref())?
+                .missing
+                .into_iter()
+                .map(|x| x.0)
+                .collect()
+        }
+    };
+
+    Ok(StatusReport {
+        git_changes: git_changes(&(get_base_dir()? + &theme))?,
+        theme,
+        device,
+        configs,
+        skipped,
+        missing_dependencies,
+    })
+}
+
+pub fn config_status(
+    config: &Config,
+    variables: &BTreeMap<String, String>,
+    target_root: Option<&Path>,
+) -> ConfigResult<ConfigStatus> {
+    let target = config.target(target_root);
+    Ok(ConfigStatus {
+        name: config.name.clone(),
+        state: config_state(config, variables, target_root)?,
+        attribute_drift: attribute_drift(&target, &config.attributes)?,
+        target,
+    })
+}
+
+fn config_state(
+    config: &Config,
+    variables: &BTreeMap<String, String>,
+    target_root: Option<&Path>,
+) -> ConfigResult<EntryState> {
+    let target = config.target(target_root);
+    let meta = match std::fs::symlink_metadata(&target) {
+        Ok(meta) => meta,
+        Err(_) => return Ok(EntryState::Missing),
+    };
+
+    if let (Some(entries), true) = (config.tracked_entries()?, meta.is_dir()) {
+        return entries_state(config, &entries, variables, target_root);
+    }
+
+    let mode = config.deploy_mode();
+    if meta.file_type().is_symlink() {
+        let link = match std::fs::read_link(&target) {
+            Ok(link) => link,
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(&target)),
+        };
+        let points_to_store = resolve_link(&target, &link) == config.link_source(target_root);
+        return match (points_to_store, mode) {
+            (true, DeployMode::Symlink) => Ok(EntryState::InSync),
+            (true, _) => Ok(EntryState::ReplacedByFile),
+            (false, _) => Ok(EntryState::WrongSymlinkTarget { link }),
+        };
+    }
+
+    // Programs that save their config atomically replace the link with a regular file
+    if mode == DeployMode::Symlink || meta.is_dir() != config.conf_location.is_dir() {
+        return Ok(EntryState::ReplacedByFile);
+    }
+
+    if mode == DeployMode::Hardlink {
+        let shared = match std::fs::metadata(&config.conf_location) {
+            Ok(store) => store.dev() == meta.dev() && store.ino() == meta.ino(),
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(&config.conf_location)),
+        };
+        if shared {
+            return Ok(EntryState::InSync);
+        }
+        return match files_equal(&target, &config.conf_location)? {
+            true => Ok(EntryState::ReplacedByFile),
+            false => Ok(EntryState::Modified),
+        };
+    }
+
+    // Secrets and templates are compared against what they produce on this device
+    if let Some(content) = config.generated_content(variables)? {
+        return match std::fs::read(&target) {
+            Ok(live) if live == content => Ok(EntryState::InSync),
+            Ok(_) => Ok(EntryState::Modified),
+            Err(err) => Err(ConfigCliError::FsReadError(err).at(&target)),
+        };
+    }
+
+    match files_equal(&target, &config.conf_location)? {
+        true => Ok(EntryState::InSync),
+        false => Ok(EntryState::Modified),
+    }
+}
+
+// The first tracked file of a filtered directory that is out of sync, untracked entries don't count
+fn entries_state(
+    config: &Config,
+    entries: &[PathBuf],
+    variables: &BTreeMap<String, String>,
+    target_root: Option<&Path>,
+) -> ConfigResult<EntryState> {
+    for relative in entries {
+        let state = config_state(&config.entry(relative), variables, target_root)?;
+        if state != EntryState::InSync {
+            return Ok(state);
+        }
+    }
+
+    // A new file the filter picks up is an edit the theme doesn't have yet
+    let mut live = BTreeSet::new();
+    let filter = config.entry_filter()?;
+    collect_files(
+        &config.target(target_root),
+        Path::new(""),
+        &mut live,
+        filter.as_ref(),
+    )?;
+    match live.iter().all(|x| entries.contains(x)) {
+        true => Ok(EntryState::InSync),
+        false => Ok(EntryState::Modified),
+    }
+}
+
+fn files_equal(a: &Path, b: &Path) -> ConfigResult<bool> {
+    match (hash_path(a), hash_path(b)) {
+        (Ok(a), Ok(b)) => Ok(a == b),
+        (Err(err), _) => Err(ConfigCliError::FsReadError(err).at(a)),
+        (_, Err(err)) => Err(ConfigCliError::FsReadError(err).at(b)),
+    }
+}
+
+fn git_changes(theme_path: &str) -> ConfigResult<Vec<String>> {
+    // Themes that were never pushed are not git repositories
+    let repo = match Repository::open(theme_path) {
+        Ok(repo) => repo,
+        Err(_) => return Ok(vec![]),
+    };
+
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let statuses = try_git!(repo.statuses(Some(&mut options)));
+
+    Ok(statuses
+        .iter()
+        .map(|entry| {
+            let status = entry.status();
+            let flag = if status.contains(Status::WT_NEW) {
+                "??"
+            } else if status.intersects(Status::WT_DELETED | Status::INDEX_DELETED) {
+                " D"
+            } else if status.intersects(Status::WT_RENAMED | Status::INDEX_RENAMED) {
+                " R"
+            } else if status.intersects(Status::INDEX_NEW) {
+                " A"
+            } else {
+                " M"
+            };
+            format!("{} {}", flag, entry.path().unwrap_or_default())
+        })
+        .collect())
+}
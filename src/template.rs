@@ -0,0 +1,28 @@
+use std::collections::BTreeMap;
+
+use crate::error::ConfigCliError;
+use crate::utils::ConfigResult;
+
+/// Replaces every `{{ name }}` in the template with the value of the variable
+pub fn render(template: &str, variables: &BTreeMap<String, String>) -> ConfigResult<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = rest[start + 2..end].trim();
+        let value = variables
+            .get(name)
+            .ok_or(ConfigCliError::UnknownVariable(name.to_owned()))?;
+
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(value);
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
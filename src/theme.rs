@@ -1,9 +1,25 @@
+pub mod add_hook;
 pub mod apply;
 pub mod create;
 pub mod delete;
+pub mod inherit;
 pub mod list;
+pub mod manifest;
+pub mod migrate;
+pub mod plan;
+pub mod recover;
+pub mod set_parents;
+pub mod transaction;
 
+pub use add_hook::*;
 pub use apply::*;
 pub use create::*;
 pub use delete::*;
+pub use inherit::*;
 pub use list::*;
+pub use manifest::*;
+pub use migrate::*;
+pub use plan::*;
+pub use recover::*;
+pub use set_parents::*;
+pub use transaction::*;
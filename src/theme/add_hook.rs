@@ -0,0 +1,9 @@
+use super::{load_manifest, save_manifest};
+use crate::hook::{Hook, HookStage};
+use crate::ConfigResult;
+
+pub fn add_theme_hook(name: String, stage: HookStage, hook: Hook) -> ConfigResult<()> {
+    let mut manifest = load_manifest(&name)?;
+    manifest.hooks.stage_mut(stage).push(hook);
+    save_manifest(&name, &manifest)
+}
@@ -1,73 +1,243 @@
-use std::path::Path;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 
-use crate::config::{Config, ConfigFile};
+use super::{plan_theme, theme_chain, PlannedFile, ThemePlan, Transaction};
+use crate::backup::Backup;
+use crate::config::{attributes_below, restore_attributes, DeployMode};
+use crate::dependency::Installation;
 use crate::error::ConfigCliError;
-use crate::{
-    copy_dir_all, get_base_dir, try_copy_recursive, try_create_file, try_read_and_parse,
-    try_write_file, ConfigResult, CurrentTheme,
-};
-
-fn apply_config(config: &Config, force: &bool) -> ConfigResult<()> {
-    if config.conf_location.is_file() && force == &false {
-        return Err(ConfigCliError::InvalidConfigLocation(
-            config.conf_location.to_string_lossy().to_string(),
-        ));
+use crate::hook::{run_hooks, run_theme_hooks, HookContext, HookRun, HookStage};
+use crate::package_manager::get_package_manager;
+use crate::utils::relative_link;
+use crate::{get_state_dir, try_create_file, try_write_file, ConfigResult, CurrentTheme};
+
+// What is put at a target, hard links are only made when swapping because the staging area may
+// be on another filesystem than the target
+enum Staged {
+    Path(PathBuf),
+    Hardlink(PathBuf),
+}
+
+impl Staged {
+    // The file that ends up at the target, a hard linked one is the store file itself
+    fn path(&self) -> &Path {
+        match self {
+            Staged::Path(path) | Staged::Hardlink(path) => path,
+        }
+    }
+}
+
+// Stages every config and the new current theme file, then swaps them in with the theme file last
+//
+// Below a target root the store content is mirrored first and the links point to the mirror,
+// while the current theme of this machine is left alone.
+fn apply_configs(transaction: &mut Transaction, plan: &ThemePlan) -> ConfigResult<()> {
+    let mut staged: Vec<(Staged, PathBuf)> = vec![];
+    for (i, file) in plan.files.iter().enumerate() {
+        let id = i.to_string();
+        // A link shows the store, so that is where the attributes go, before it gets mirrored
+        if file.mode == DeployMode::Symlink {
+            restore_attributes(&file.source, &file.attributes)?;
+        }
+        if let Some(store_copy) = &file.store_copy {
+            staged.push((
+                Staged::Path(transaction.stage(&(id.clone() + "-store"), &file.source)?),
+                store_copy.clone(),
+            ));
+        }
+
+        let Some(entries) = &file.entries else {
+            let source = (file.source.as_path(), file.store_copy.as_deref());
+            let staged_file = stage_file(transaction, &id, file, source, &file.target)?;
+            if file.mode != DeployMode::Symlink {
+                restore_attributes(staged_file.path(), &file.attributes)?;
+            }
+            staged.push((staged_file, file.target.clone()));
+            continue;
+        };
+
+        // A link to the whole directory from an earlier deploy makes way for a real directory
+        let is_link = std::fs::symlink_metadata(&file.target).is_ok_and(|x| x.is_symlink());
+        if is_link {
+            staged.push((
+                Staged::Path(transaction.stage_dir(&(id.clone() + "-dir"))?),
+                file.target.clone(),
+            ));
+        }
+        for (j, relative) in entries.iter().enumerate() {
+            let source = file.source.join(relative);
+            let store_copy = file.store_copy.as_ref().map(|x| x.join(relative));
+            let target = file.target.join(relative);
+            let staged_file = stage_file(
+                transaction,
+                &format!("{}-{}", id, j),
+                file,
+                (&source, store_copy.as_deref()),
+                &target,
+            )?;
+            if file.mode != DeployMode::Symlink {
+                let attributes = attributes_below(&file.attributes, relative);
+                restore_attributes(staged_file.path(), &attributes)?;
+            }
+            staged.push((staged_file, target));
+        }
     }
-    if config.conf_location.is_dir() && force == &false {
-        return Err(ConfigCliError::ConfigLocationUsed(
-            config.conf_location.to_string_lossy().to_string(),
+
+    let current_theme_path = get_state_dir()?.join("current_theme.toml");
+    if plan.target_root.is_none() {
+        staged.push((
+            Staged::Path(stage_current_theme(transaction, plan.theme.clone())?),
+            current_theme_path,
         ));
     }
-    try_copy_recursive!(config.conf_location.clone(), config.symlink.clone());
+
+    for (staged, target) in staged {
+        match staged {
+            Staged::Path(staged) => transaction.swap(&staged, &target)?,
+            Staged::Hardlink(source) => transaction.swap_hardlink(&source, &target)?,
+        }
+    }
     Ok(())
 }
 
-// Change the current theme file to the new theme
-fn change_current_theme(name: String) -> ConfigResult<()> {
-    let current_theme_path = get_base_dir()? + "current_theme.toml";
+// Stages what the mode puts at the target, the source coming with its mirror below a target root
+fn stage_file(
+    transaction: &Transaction,
+    id: &str,
+    file: &PlannedFile,
+    (source, store_copy): (&Path, Option<&Path>),
+    target: &Path,
+) -> ConfigResult<Staged> {
+    let staged = match (&file.content, file.mode, store_copy) {
+        (Some(content), _, _) => {
+            let permissions = if file.secret { 0o600 } else { 0o644 };
+            transaction.stage_content(id, content, permissions)?
+        }
+        (None, DeployMode::Symlink, Some(store_copy)) => {
+            transaction.stage_symlink(id, &relative_link(target, store_copy))?
+        }
+        (None, DeployMode::Symlink, None) => transaction.stage_symlink(id, source)?,
+        (None, DeployMode::Hardlink, _) => return Ok(Staged::Hardlink(source.to_path_buf())),
+        (None, _, _) => transaction.stage(id, source)?,
+    };
+    Ok(Staged::Path(staged))
+}
 
-    if !Path::new(&current_theme_path).exists() {
-        try_create_file!(current_theme_path.clone());
+// Writes the new current theme file into the staging area
+fn stage_current_theme(transaction: &Transaction, name: String) -> ConfigResult<PathBuf> {
+    let staged_path = transaction.staged_path("current_theme.toml");
+
+    if !Path::new(&staged_path).exists() {
+        try_create_file!(staged_path.clone());
     }
 
     try_write_file!(
-        current_theme_path.clone(),
+        staged_path.clone(),
         &CurrentTheme {
             current_theme: name
         }
     );
 
-    Ok(())
+    Ok(staged_path)
 }
 
-pub fn use_theme(name: String, force: bool, device: Option<String>) -> ConfigResult<()> {
-    let theme_path = get_base_dir()? + &name;
+// Runs the apply hooks of every planned config
+fn run_config_hooks(
+    theme: &str,
+    files: &[PlannedFile],
+    stage: HookStage,
+    runs: &mut Vec<HookRun>,
+) -> ConfigResult<()> {
+    for file in files {
+        let context = HookContext {
+            theme,
+            config: Some((&file.config, &file.target)),
+        };
+        run_hooks(&file.hooks, stage, &context, runs)?;
+    }
+    Ok(())
+}
 
-    if !Path::new(&theme_path).exists() {
-        return Err(ConfigCliError::InvalidThemeName(name));
+// Everything that runs after the files were swapped in, a failure here rolls the switch back
+fn run_post_hooks(
+    plan: &ThemePlan,
+    previous_theme: Option<&str>,
+    runs: &mut Vec<HookRun>,
+) -> ConfigResult<()> {
+    run_config_hooks(&plan.theme, &plan.files, HookStage::PostApply, runs)?;
+    run_theme_hooks(&plan.theme, HookStage::PostApply, runs)?;
+    if let Some(previous_theme) = previous_theme {
+        run_theme_hooks(previous_theme, HookStage::PostRemove, runs)?;
     }
+    Ok(())
+}
+
+/// What switching the theme did besides deploying the files
+#[derive(Debug, Serialize)]
+pub struct ThemeSwitch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installation: Option<Installation>,
+    pub hooks: Vec<HookRun>,
+}
 
-    let config_file_path = theme_path.clone() + "/configs.toml";
-    let config_file = try_read_and_parse!(config_file_path, ConfigFile);
+pub fn use_theme(
+    name: String,
+    force: bool,
+    device: Option<String>,
+    target_root: Option<PathBuf>,
+) -> ConfigResult<ThemeSwitch> {
+    let mut plan = plan_theme(name.clone(), force, device, target_root)?;
 
-    change_current_theme(name)?;
+    if let Some(conflict) = plan.conflicts().next() {
+        if !force {
+            return Err(ConfigCliError::ConfigLocationUsed(
+                conflict.target.to_string_lossy().to_string(),
+            ));
+        }
+    }
 
-    for config in config_file.globals {
-        apply_config(&config, &force)?;
+    let mut installation = None;
+    if !plan.dependencies.missing.is_empty() {
+        let package_manager = get_package_manager(&name, plan.device.as_deref())?;
+        let dependencies = std::mem::take(&mut plan.dependencies);
+        installation = Some(dependencies.execute(package_manager.as_ref())?);
     }
 
-    if device.is_some() {
-        let device_configs: Vec<Config> = config_file
-            .device_bounds
-            .iter()
-            .filter(|x| x.0 == device.clone().unwrap())
-            .map(|x| x.1.clone())
-            .collect();
+    // The hooks of the theme being replaced only run when switching to another theme
+    let previous_theme = plan
+        .previous_theme
+        .as_deref()
+        .filter(|x| *x != name && theme_chain(x).is_ok());
 
-        for config in device_configs {
-            apply_config(&config, &force)?;
+    // Hooks act on this machine, so none run when deploying below a target root
+    let runs_hooks = plan.target_root.is_none();
+    let mut runs = vec![];
+    if runs_hooks {
+        if let Some(previous_theme) = previous_theme {
+            run_theme_hooks(previous_theme, HookStage::PreRemove, &mut runs)?;
         }
+        run_theme_hooks(&name, HookStage::PreApply, &mut runs)?;
+        run_config_hooks(&name, &plan.files, HookStage::PreApply, &mut runs)?;
     }
 
-    Ok(())
+    // Only conflicting targets hold something that isn't in a theme already
+    let mut backup = Backup::begin(&format!("theme use {}", name))?;
+    for path in plan.conflicts().flat_map(|x| x.replaced_paths()) {
+        backup.snapshot(&path)?;
+    }
+    backup.finish()?;
+
+    let mut transaction = Transaction::begin()?;
+    let applied = apply_configs(&mut transaction, &plan).and_then(|_| match runs_hooks {
+        true => run_post_hooks(&plan, previous_theme, &mut runs),
+        false => Ok(()),
+    });
+    match applied {
+        Ok(_) => transaction.commit()?,
+        Err(err) => return Err(transaction.rollback(err)),
+    }
+    Ok(ThemeSwitch {
+        installation,
+        hooks: runs,
+    })
 }
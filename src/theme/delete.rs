@@ -1,73 +1,59 @@
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
-use crate::config::{Config, ConfigFile};
+use super::{list_themes, load_manifest, save_manifest};
+use crate::backup::Backup;
 use crate::error::ConfigCliError;
-use crate::utils::{copy_dir_all, get_base_dir};
-use crate::{
-    try_copy_recursive, try_delete, try_delete_recursive, try_read_and_parse, ConfigResult,
-};
+use crate::utils::get_base_dir;
+use crate::{try_delete_recursive, ConfigResult};
 
-pub fn remove_theme(name: String) -> ConfigResult<()> {
+/// Deletes the theme, leaving the targets it deployed standalone
+///
+/// Themes inheriting from it are refused unless forced, which drops it from their parents.
+pub fn remove_theme(name: String, force: bool) -> ConfigResult<()> {
     let theme_path = get_base_dir()? + &name;
 
-    let configs_to_remove = try_read_and_parse!(
-        Path::new(&(theme_path.clone() + "configs.toml")),
-        ConfigFile
-    );
-
-    let mut all_configs: Vec<Config> = configs_to_remove.globals;
-
-    all_configs.extend(
-        configs_to_remove
-            .device_bounds
-            .into_iter()
-            .map(|x| x.1)
-            .collect::<Vec<Config>>(),
-    );
-
-    let config_loactions: Vec<PathBuf> = all_configs
-        .clone()
-        .into_iter()
-        .map(|x| x.conf_location)
-        .collect();
-
-    let mut saved_configs: Vec<Config> = vec![];
-
-    let read_dir = match std::fs::read_dir(get_base_dir()?) {
-        Ok(dir) => dir,
-        Err(err) => return Err(ConfigCliError::FsReadError(err)),
-    };
-
-    for theme in read_dir {
-        let theme_path = match theme {
-            Ok(path) => path.path(),
-            Err(err) => return Err(ConfigCliError::FsReadError(err)),
-        };
-        let configs = try_read_and_parse!(theme_path, ConfigFile);
-
-        saved_configs.extend(
-            configs
-                .globals
-                .into_iter()
-                .filter(|x| config_loactions.contains(&x.conf_location))
-                .collect::<Vec<Config>>(),
-        );
+    if !Path::new(&theme_path).exists() {
+        return Err(ConfigCliError::InvalidThemeName(name));
     }
 
-    let unsaved_configs = all_configs
-        .into_iter()
-        .filter(|x| saved_configs.contains(&x))
-        .collect::<Vec<Config>>();
-
-    // Put the configs that aren't used anywhere else back into their original location
-    for unsaved_config in unsaved_configs {
-        if unsaved_config.active {
-            try_delete!(unsaved_config.symlink.clone());
+    let mut children = vec![];
+    for path in list_themes()? {
+        let theme = Path::new(&path).file_name().unwrap_or_default();
+        let theme = theme.to_string_lossy().to_string();
+        let manifest = load_manifest(&theme)?;
+        if manifest.parents.contains(&name) {
+            children.push((theme, manifest));
         }
+    }
+    if !children.is_empty() && !force {
+        let mut names: Vec<&str> = children.iter().map(|x| x.0.as_str()).collect();
+        names.sort();
+        return Err(ConfigCliError::StillInUse(format!(
+            "themes inheriting from {}: {}",
+            name,
+            names.join(", ")
+        )));
+    }
 
-        try_copy_recursive!(unsaved_config.conf_location, unsaved_config.symlink);
+    let configs_to_remove = load_manifest(&name)?.configs;
+
+    let mut backup = Backup::begin(&format!("theme remove {}", name))?;
+    // Targets still deployed from this theme get a standalone copy before the store is gone
+    for config in configs_to_remove
+        .globals
+        .iter()
+        .chain(configs_to_remove.device_bounds.iter().map(|x| &x.1))
+    {
+        config.detach(&mut backup)?;
     }
 
+    backup.snapshot(Path::new(&theme_path))?;
     try_delete_recursive!(theme_path);
+    backup.finish()?;
+
+    for (child, mut manifest) in children {
+        manifest.parents.retain(|x| *x != name);
+        save_manifest(&child, &manifest)?;
+    }
     Ok(())
 }
@@ -0,0 +1,141 @@
+use serde::Serialize;
+use std::path::Path;
+
+use super::load_manifest;
+use crate::config::Config;
+use crate::device::device_lineage;
+use crate::error::ConfigCliError;
+use crate::{get_base_dir, ConfigResult};
+
+/// A config as resolved through the inheritance chain
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveConfig {
+    /// The theme in the chain the config is defined in
+    pub layer: String,
+    pub device: Option<String>,
+    #[serde(flatten)]
+    pub config: Config,
+}
+
+/// A config left out because its `when` condition does not hold on this machine
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedConfig {
+    pub config: String,
+    pub when: String,
+    /// The values the condition looked at
+    pub reason: String,
+}
+
+/// Returns the theme and all of its ancestors, ordered from the root to the theme itself
+pub fn theme_chain(theme: &str) -> ConfigResult<Vec<String>> {
+    let mut chain = vec![];
+    visit_theme(theme, &mut chain, &mut vec![])?;
+    Ok(chain)
+}
+
+// Parents are visited depth first, so every layer comes after everything it inherits from
+fn visit_theme(
+    theme: &str,
+    chain: &mut Vec<String>,
+    visiting: &mut Vec<String>,
+) -> ConfigResult<()> {
+    if chain.iter().any(|x| x == theme) {
+        return Ok(());
+    }
+    if visiting.iter().any(|x| x == theme) {
+        visiting.push(theme.to_owned());
+        return Err(ConfigCliError::ThemeCycle(visiting.join(" -> ")));
+    }
+    if !Path::new(&(get_base_dir()? + theme)).exists() {
+        return Err(ConfigCliError::InvalidThemeName(theme.to_owned()));
+    }
+
+    visiting.push(theme.to_owned());
+    for parent in load_manifest(theme)?.parents {
+        visit_theme(&parent, chain, visiting)
+            .map_err(|err| err.context(format!("parent of theme {}", theme)))?;
+    }
+    visiting.pop();
+
+    chain.push(theme.to_owned());
+    Ok(())
+}
+
+/// Every config of the chain, where a layer replaces configs of its ancestors with the same name
+pub fn layered_configs(theme: &str) -> ConfigResult<Vec<EffectiveConfig>> {
+    let mut configs: Vec<EffectiveConfig> = vec![];
+
+    for layer in theme_chain(theme)? {
+        let config_file = load_manifest(&layer)?.configs;
+        let entries = config_file.globals.into_iter().map(|x| (None, x)).chain(
+            config_file
+                .device_bounds
+                .into_iter()
+                .map(|x| (Some(x.0), x.1)),
+        );
+
+        for (device, config) in entries {
+            configs.retain(|x| x.device != device || x.config.name != config.name);
+            configs.push(EffectiveConfig {
+                layer: layer.clone(),
+                device,
+                config,
+            });
+        }
+    }
+    Ok(configs)
+}
+
+/// Returns the configs of the theme that are applied for the device
+///
+/// Configs bound to a name of the device lineage replace global configs and configs bound to
+/// earlier names with the same name, see [`device_lineage`].
+pub fn effective_configs(theme: &str, device: Option<&str>) -> ConfigResult<Vec<EffectiveConfig>> {
+    let layered = layered_configs(theme)?;
+    let lineage = match device {
+        Some(device) => device_lineage(theme, device)?,
+        None => vec![],
+    };
+
+    let (device_configs, mut configs): (Vec<EffectiveConfig>, Vec<EffectiveConfig>) =
+        layered.into_iter().partition(|x| x.device.is_some());
+
+    for name in &lineage {
+        for bound in device_configs
+            .iter()
+            .filter(|x| x.device.as_ref() == Some(name))
+        {
+            configs.retain(|x| x.config.name != bound.config.name);
+            configs.push(bound.clone());
+        }
+    }
+    Ok(configs)
+}
+
+pub fn theme_configs(theme: &str, device: Option<&str>) -> ConfigResult<Vec<Config>> {
+    Ok(effective_configs(theme, device)?
+        .into_iter()
+        .map(|x| x.config)
+        .collect())
+}
+
+/// The configs of the theme applied for the device on this machine and the ones whose condition
+/// does not hold
+pub fn applied_configs(
+    theme: &str,
+    device: Option<&str>,
+) -> ConfigResult<(Vec<Config>, Vec<SkippedConfig>)> {
+    let mut applied = vec![];
+    let mut skipped = vec![];
+    for config in theme_configs(theme, device)? {
+        match config.skip_reason()? {
+            Some(reason) => skipped.push(SkippedConfig {
+                when: config.when.clone().unwrap_or_default(),
+                config: config.name,
+                reason,
+            }),
+            None => applied.push(config),
+        }
+    }
+    Ok((applied, skipped))
+}
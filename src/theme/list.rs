@@ -5,18 +5,21 @@ pub fn list_themes() -> ConfigResult<Vec<String>> {
     let mut ret: Vec<String> = vec![];
     let theme_path = get_base_dir()?;
 
-    let read_dir = match std::fs::read_dir(theme_path) {
+    let read_dir = match std::fs::read_dir(&theme_path) {
         Ok(dir) => dir,
-        Err(err) => return Err(ConfigCliError::FsReadError(err)),
+        Err(err) => return Err(ConfigCliError::FsReadError(err).at(&theme_path)),
     };
 
     for theme in read_dir {
         let theme_path = match theme {
             Ok(path) => path.path(),
-            Err(err) => return Err(ConfigCliError::FsReadError(err)),
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(get_base_dir()?)),
         };
-        if theme_path.clone().is_dir() {
-            ret.push(theme_path.to_str().unwrap().to_owned());
+        let is_hidden = theme_path
+            .file_name()
+            .is_some_and(|x| x.to_string_lossy().starts_with('.'));
+        if theme_path.clone().is_dir() && !is_hidden {
+            ret.push(theme_path.to_string_lossy().to_string());
         }
     }
 
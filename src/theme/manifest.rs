@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::backup::Backup;
+use crate::config::ConfigFile;
+use crate::dependency::DependencyFile;
+use crate::device::DeviceFile;
+use crate::error::ConfigCliError;
+use crate::hook::Hooks;
+use crate::variable::VariableFile;
+use crate::{get_base_dir, try_create_file, try_read_file, try_write_file, ConfigResult};
+
+/// Version of the manifest layout written by this build
+pub const SCHEMA_VERSION: i64 = 2;
+
+// Files a theme was split over before the manifest, the misspelled `config.toml` was written by older versions
+const LEGACY_FILES: [&str; 5] = [
+    "configs.toml",
+    "config.toml",
+    "dependencies.toml",
+    "devices.toml",
+    "variables.toml",
+];
+
+/// Everything a theme declares, stored in its `theme.toml`
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ThemeManifest {
+    pub schema_version: i64,
+    /// Themes this theme inherits configs, dependencies and devices from, later ones win
+    #[serde(default)]
+    pub parents: Vec<String>,
+    /// Run when the theme is switched to or away from
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub configs: ConfigFile,
+    #[serde(default)]
+    pub dependencies: DependencyFile,
+    #[serde(default)]
+    pub devices: DeviceFile,
+    #[serde(default)]
+    pub variables: VariableFile,
+}
+
+impl Default for ThemeManifest {
+    fn default() -> Self {
+        ThemeManifest {
+            schema_version: SCHEMA_VERSION,
+            parents: vec![],
+            hooks: Hooks::default(),
+            configs: ConfigFile::default(),
+            dependencies: DependencyFile::default(),
+            devices: DeviceFile::default(),
+            variables: VariableFile::default(),
+        }
+    }
+}
+
+// Upgrades the raw manifest by one version, the entry at index n upgrades version n + 1
+type Migration = fn(&Path, toml::Table) -> ConfigResult<toml::Table>;
+const MIGRATIONS: [Migration; 1] = [merge_split_files];
+
+fn theme_dir(theme: &str) -> ConfigResult<String> {
+    let theme_path = get_base_dir()? + theme;
+    if !Path::new(&theme_path).exists() {
+        return Err(ConfigCliError::InvalidThemeName(theme.to_owned()));
+    }
+    Ok(theme_path)
+}
+
+fn read_table(path: &Path) -> ConfigResult<toml::Table> {
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    let content = try_read_file!(path);
+    let content = match std::str::from_utf8(&content) {
+        Ok(content) => content,
+        Err(err) => return Err(ConfigCliError::StringConversionError(err).at(path)),
+    };
+    match toml::from_str(content) {
+        Ok(table) => Ok(table),
+        Err(err) => Err(ConfigCliError::DeserializeError(err).at(path)),
+    }
+}
+
+// Themes without a schema version are the split layout of version 1
+fn table_version(table: &toml::Table) -> i64 {
+    table
+        .get("schema_version")
+        .and_then(|x| x.as_integer())
+        .unwrap_or(1)
+}
+
+/// Version 1 kept configs, dependencies, devices and variables in files of their own
+fn merge_split_files(theme_dir: &Path, mut table: toml::Table) -> ConfigResult<toml::Table> {
+    let sections = [
+        ("configs", "config.toml"),
+        ("configs", "configs.toml"),
+        ("dependencies", "dependencies.toml"),
+        ("devices", "devices.toml"),
+        ("variables", "variables.toml"),
+    ];
+    for (section, file) in sections {
+        let path = theme_dir.join(file);
+        if path.exists() {
+            table.insert(section.to_owned(), toml::Value::Table(read_table(&path)?));
+        }
+    }
+    table.insert("schema_version".to_owned(), toml::Value::Integer(2));
+    Ok(table)
+}
+
+/// Reads the manifest of the theme, upgrading older layouts in memory
+pub fn load_manifest(theme: &str) -> ConfigResult<ThemeManifest> {
+    let theme_dir = theme_dir(theme)?;
+    read_manifest(Path::new(&theme_dir)).map_err(|err| err.in_theme(theme))
+}
+
+fn read_manifest(theme_dir: &Path) -> ConfigResult<ThemeManifest> {
+    let mut table = read_table(&theme_dir.join("theme.toml"))?;
+
+    let version = table_version(&table);
+    if !(1..=SCHEMA_VERSION).contains(&version) {
+        return Err(ConfigCliError::UnsupportedSchemaVersion(format!(
+            "version {}, this build supports up to {}",
+            version, SCHEMA_VERSION
+        )));
+    }
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        table = migration(theme_dir, table)?;
+    }
+
+    match toml::Value::Table(table).try_into() {
+        Ok(manifest) => Ok(manifest),
+        Err(err) => Err(ConfigCliError::DeserializeError(err).at(theme_dir.join("theme.toml"))),
+    }
+}
+
+/// Writes the manifest, backing up and removing the files of older layouts it replaces
+pub fn save_manifest(theme: &str, manifest: &ThemeManifest) -> ConfigResult<()> {
+    let theme_dir = theme_dir(theme)?;
+    write_manifest(theme, theme_dir, manifest).map_err(|err| err.in_theme(theme))
+}
+
+fn write_manifest(theme: &str, theme_dir: String, manifest: &ThemeManifest) -> ConfigResult<()> {
+    let manifest_path = theme_dir.clone() + "/theme.toml";
+
+    if !Path::new(&manifest_path).exists() {
+        try_create_file!(manifest_path.clone());
+    }
+    try_write_file!(manifest_path, manifest);
+
+    let legacy_files: Vec<String> = LEGACY_FILES
+        .iter()
+        .map(|x| theme_dir.clone() + "/" + x)
+        .filter(|x| Path::new(x).exists())
+        .collect();
+    if legacy_files.is_empty() {
+        return Ok(());
+    }
+
+    let mut backup = Backup::begin(&format!("theme migrate {}", theme))?;
+    for file in legacy_files {
+        backup.snapshot(Path::new(&file))?;
+        if let Err(err) = std::fs::remove_file(&file) {
+            return Err(ConfigCliError::DeleteError(err).at(&file));
+        }
+    }
+    backup.finish()?;
+    Ok(())
+}
+
+/// Whether the theme on disk is stored in an older layout
+pub fn needs_migration(theme: &str) -> ConfigResult<bool> {
+    let theme_dir = theme_dir(theme)?;
+    let table = read_table(&Path::new(&theme_dir).join("theme.toml"))?;
+    Ok(table_version(&table) < SCHEMA_VERSION
+        || LEGACY_FILES
+            .iter()
+            .any(|x| Path::new(&(theme_dir.clone() + "/" + x)).exists()))
+}
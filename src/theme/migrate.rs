@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use super::{list_themes, load_manifest, needs_migration, save_manifest};
+use crate::ConfigResult;
+
+/// Rewrites themes stored in an older layout as the current manifest, all themes if none are given
+///
+/// Returns the themes that were upgraded.
+pub fn migrate_themes(names: Vec<String>) -> ConfigResult<Vec<String>> {
+    let names = match names.is_empty() {
+        true => list_themes()?
+            .iter()
+            .filter_map(|x| Path::new(x).file_name())
+            .map(|x| x.to_string_lossy().to_string())
+            .collect(),
+        false => names,
+    };
+
+    let mut migrated = vec![];
+    for name in names {
+        if !needs_migration(&name)? {
+            continue;
+        }
+        save_manifest(&name, &load_manifest(&name)?)?;
+        migrated.push(name);
+    }
+    Ok(migrated)
+}
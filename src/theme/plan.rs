@@ -0,0 +1,214 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::{applied_configs, theme_chain, SkippedConfig};
+use crate::config::{Config, DeployMode, FileAttributes};
+use crate::dependency::{collect_dependencies, InstallPlan};
+use crate::device::resolve_device;
+use crate::hook::Hooks;
+use crate::package_manager::get_package_manager;
+use crate::utils::{hash_path, resolve_link};
+use crate::variable::resolve_variables;
+use crate::{get_current_theme, ConfigResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    /// Nothing exists at the target yet
+    Create,
+    /// A regular file or directory at the target gets overwritten
+    Overwrite,
+    /// A symlink at the target gets replaced
+    ReplaceSymlink,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    pub config: String,
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub action: FileAction,
+    /// True if the target is not managed by the theme store and needs `--force` to be replaced
+    pub conflict: bool,
+    pub mode: DeployMode,
+    /// Decrypted secrets are only readable by the owner once written
+    pub secret: bool,
+    /// Below a target root, where the store content is mirrored for the relative link to point to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_copy: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// Decrypted or rendered content, written instead of copying the source
+    #[serde(skip)]
+    pub content: Option<Vec<u8>>,
+    /// The tracked files of a filtered directory, deployed one by one into a real directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entries: Option<Vec<PathBuf>>,
+    /// Mode bits and owners restored on what gets deployed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<FileAttributes>,
+}
+
+impl PlannedFile {
+    /// The paths the deploy replaces, only the tracked files inside a filtered directory
+    pub fn replaced_paths(&self) -> Vec<PathBuf> {
+        let is_link = std::fs::symlink_metadata(&self.target).is_ok_and(|x| x.is_symlink());
+        match &self.entries {
+            Some(entries) if !is_link => entries.iter().map(|x| self.target.join(x)).collect(),
+            _ => vec![self.target.clone()],
+        }
+    }
+}
+
+/// Everything `use_theme` would do, computed without touching the disk
+#[derive(Debug, Serialize)]
+pub struct ThemePlan {
+    pub theme: String,
+    pub previous_theme: Option<String>,
+    /// The device given or detected, if any
+    pub device: Option<String>,
+    pub force: bool,
+    /// Directory the targets are deployed below instead of `/`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_root: Option<PathBuf>,
+    pub files: Vec<PlannedFile>,
+    /// Configs left out because their condition does not hold
+    pub skipped: Vec<SkippedConfig>,
+    pub dependencies: InstallPlan,
+}
+
+impl ThemePlan {
+    pub fn conflicts(&self) -> impl Iterator<Item = &PlannedFile> {
+        self.files.iter().filter(|x| x.conflict)
+    }
+}
+
+pub fn plan_config(
+    config: &Config,
+    variables: &BTreeMap<String, String>,
+    target_root: Option<&Path>,
+) -> ConfigResult<PlannedFile> {
+    let target = config.target(target_root);
+    let mode = config.deploy_mode();
+    let store_copy = match mode {
+        DeployMode::Symlink => target_root.map(|_| config.link_source(target_root)),
+        _ => None,
+    };
+
+    let (action, conflict) = match std::fs::symlink_metadata(&target) {
+        Err(_) => (FileAction::Create, false),
+        Ok(meta) if meta.file_type().is_symlink() => {
+            // Links into the theme store are ours to replace
+            let points_to_store = std::fs::read_link(&target)
+                .map(|x| resolve_link(&target, &x) == config.link_source(target_root))
+                .unwrap_or(false);
+            (FileAction::ReplaceSymlink, !points_to_store)
+        }
+        Ok(_) => (FileAction::Overwrite, true),
+    };
+
+    let entries = config.tracked_entries()?;
+    let content = match entries {
+        Some(_) => None,
+        None => config.generated_content(variables)?,
+    };
+
+    let (action, conflict) = match (&entries, action) {
+        // A filtered directory is deployed file by file next to the entries it leaves out
+        (Some(entries), FileAction::Overwrite) => {
+            let planned = entries
+                .iter()
+                .map(|x| plan_config(&config.entry(x), variables, target_root))
+                .collect::<ConfigResult<Vec<PlannedFile>>>()?;
+            let action = planned
+                .iter()
+                .map(|x| x.action)
+                .find(|x| *x != FileAction::Create)
+                .unwrap_or(FileAction::Create);
+            (action, planned.iter().any(|x| x.conflict))
+        }
+        (Some(_), action) => (action, conflict),
+        // Overwriting a target that already holds the theme's content loses nothing
+        (None, action) => (
+            action,
+            conflict
+                && match &content {
+                    Some(content) => std::fs::read(&target)
+                        .map(|x| &x != content)
+                        .unwrap_or(true),
+                    None => match (hash_path(&target), hash_path(&config.conf_location)) {
+                        (Ok(a), Ok(b)) => a != b,
+                        _ => true,
+                    },
+                },
+        ),
+    };
+
+    Ok(PlannedFile {
+        config: config.name.clone(),
+        source: config.conf_location.clone(),
+        target,
+        action,
+        conflict,
+        mode,
+        secret: config.secret,
+        store_copy,
+        hooks: config.hooks.clone(),
+        content,
+        entries,
+        attributes: config.attributes.clone(),
+    })
+}
+
+pub fn plan_theme(
+    name: String,
+    force: bool,
+    device: Option<String>,
+    target_root: Option<PathBuf>,
+) -> ConfigResult<ThemePlan> {
+    theme_chain(&name)?;
+    // The facts of this machine say nothing about the one below a target root
+    let device = match target_root {
+        Some(_) => device,
+        None => resolve_device(&name, device)?,
+    };
+    let (configs, skipped) = applied_configs(&name, device.as_deref())?;
+    let variables = resolve_variables(&name, device.as_deref())?;
+
+    // A package manager is only needed if the theme has dependencies at all, and packages are
+    // installed on this machine, never below a target root
+    let dependencies = match target_root {
+        Some(_) => vec![],
+        None => collect_dependencies(&name, device.as_deref())?,
+    };
+    let dependencies = match dependencies.is_empty() {
+        true => InstallPlan::default(),
+        false => {
+            let package_manager = get_package_manager(&name, device.as_deref())?;
+            InstallPlan::from_dependencies(dependencies, package_manager.as_ref())?
+        }
+    };
+
+    let files = configs
+        .iter()
+        .map(|x| plan_config(x, &variables, target_root.as_deref()))
+        .collect::<ConfigResult<Vec<PlannedFile>>>()?;
+
+    // Deploying below a target root leaves the theme of this machine as it is
+    let previous_theme = match target_root {
+        Some(_) => None,
+        None => get_current_theme().ok(),
+    };
+
+    Ok(ThemePlan {
+        previous_theme,
+        device,
+        force,
+        target_root,
+        files,
+        skipped,
+        dependencies,
+        theme: name,
+    })
+}
@@ -0,0 +1,10 @@
+use super::Transaction;
+use crate::utils::ConfigResult;
+
+/// Rolls back a theme switch that was interrupted, returning the targets that were put back
+pub fn recover_theme_switch() -> ConfigResult<Vec<String>> {
+    Ok(Transaction::recover()?
+        .into_iter()
+        .map(|x| x.to_string_lossy().to_string())
+        .collect())
+}
@@ -0,0 +1,16 @@
+use super::{load_manifest, save_manifest, theme_chain};
+use crate::ConfigResult;
+
+pub fn set_parents(name: String, parents: Vec<String>) -> ConfigResult<()> {
+    let mut manifest = load_manifest(&name)?;
+    let previous_parents = std::mem::replace(&mut manifest.parents, parents);
+    save_manifest(&name, &manifest)?;
+
+    // Put the old parents back if the new ones are missing or form a cycle
+    if let Err(err) = theme_chain(&name) {
+        manifest.parents = previous_parents;
+        save_manifest(&name, &manifest)?;
+        return Err(err);
+    }
+    Ok(())
+}
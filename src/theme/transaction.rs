@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::error::ConfigCliError;
+use crate::utils::{get_state_dir, move_path, remove_path};
+use crate::{
+    copy_dir_all, try_copy_recursive, try_move, try_read_and_parse, try_symlink, ConfigResult,
+};
+
+const JOURNAL: &str = "journal.toml";
+
+/// Swaps files into place while keeping the originals, so a failed theme switch can be undone
+///
+/// Every target is written to a journal before it is touched, so a switch that was interrupted
+/// can still be rolled back by [`Transaction::recover`].
+pub struct Transaction {
+    dir: PathBuf,
+    journal: Journal,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Journal {
+    #[serde(default)]
+    swapped: Vec<Swapped>,
+}
+
+// A target that was touched, with the backup of what was there before
+#[derive(Serialize, Deserialize)]
+struct Swapped {
+    target: PathBuf,
+    backup: Option<PathBuf>,
+}
+
+impl Transaction {
+    fn dir() -> ConfigResult<PathBuf> {
+        Ok(get_state_dir()?.join(".transaction"))
+    }
+
+    pub fn begin() -> ConfigResult<Self> {
+        let dir = Self::dir()?;
+
+        if dir.exists() {
+            return Err(ConfigCliError::TransactionInProgress(
+                dir.to_string_lossy().to_string(),
+            ));
+        }
+
+        for sub_dir in ["staged", "backup"] {
+            match std::fs::create_dir_all(dir.join(sub_dir)) {
+                Ok(_) => (),
+                Err(err) => return Err(ConfigCliError::FileCreationError(err).at(&dir)),
+            }
+        }
+
+        Ok(Transaction {
+            dir,
+            journal: Journal::default(),
+        })
+    }
+
+    /// Rolls back a transaction that was left behind by a crash or a failed rollback
+    ///
+    /// Returns the targets whose originals were put back, nothing if there is no transaction.
+    /// Must not run while another theme switch is in progress.
+    pub fn recover() -> ConfigResult<Vec<PathBuf>> {
+        let dir = Self::dir()?;
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let journal_path = dir.join(JOURNAL);
+        let journal = match journal_path.exists() {
+            true => try_read_and_parse!(&journal_path, Journal),
+            // Interrupted before anything was swapped
+            false => Journal::default(),
+        };
+        let transaction = Transaction { dir, journal };
+        let restored = transaction
+            .journal
+            .swapped
+            .iter()
+            .map(|x| x.target.clone())
+            .collect();
+        transaction.restore()?;
+        Ok(restored)
+    }
+
+    /// Location in the staging area for new content with the given id
+    pub fn staged_path(&self, id: &str) -> PathBuf {
+        self.dir.join("staged").join(id)
+    }
+
+    /// Copies the new content into the staging area without touching the target
+    pub fn stage(&self, id: &str, source: &Path) -> ConfigResult<PathBuf> {
+        let staged = self.staged_path(id);
+        try_copy_recursive!(source, &staged);
+        Ok(staged)
+    }
+
+    /// Stages an empty directory, for targets that get their content file by file
+    pub fn stage_dir(&self, id: &str) -> ConfigResult<PathBuf> {
+        let staged = self.staged_path(id);
+        match std::fs::create_dir_all(&staged) {
+            Ok(_) => Ok(staged),
+            Err(err) => Err(ConfigCliError::FileCreationError(err).at(&staged)),
+        }
+    }
+
+    /// Stages a symlink to the source, which is moved into place as is
+    pub fn stage_symlink(&self, id: &str, source: &Path) -> ConfigResult<PathBuf> {
+        let staged = self.staged_path(id);
+        try_symlink!(source, &staged);
+        Ok(staged)
+    }
+
+    /// Writes generated content into the staging area
+    pub fn stage_content(&self, id: &str, content: &[u8], mode: u32) -> ConfigResult<PathBuf> {
+        let staged = self.staged_path(id);
+        let written = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(mode)
+            .open(&staged)
+            .and_then(|mut file| file.write_all(content));
+        match written {
+            Ok(_) => Ok(staged),
+            Err(err) => Err(ConfigCliError::FsWriteError(err).at(&staged)),
+        }
+    }
+
+    /// Backs up whatever is at the target and moves the staged content into its place
+    pub fn swap(&mut self, staged: &Path, target: &Path) -> ConfigResult<()> {
+        self.make_room(target)?;
+        try_move!(staged, target);
+        Ok(())
+    }
+
+    /// Backs up whatever is at the target and hard links the source into its place
+    ///
+    /// The link is made at the target itself, so only the store has to be on its filesystem. If
+    /// it isn't, this fails instead of leaving a standalone copy.
+    pub fn swap_hardlink(&mut self, source: &Path, target: &Path) -> ConfigResult<()> {
+        self.make_room(target)?;
+        match std::fs::hard_link(source, target) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(ConfigCliError::SymlinkError(err).at(target)),
+        }
+    }
+
+    // Moves whatever is at the target into the backups and creates the directories above it
+    fn make_room(&mut self, target: &Path) -> ConfigResult<()> {
+        let backup = match std::fs::symlink_metadata(target) {
+            Ok(_) => Some(
+                self.dir
+                    .join("backup")
+                    .join(self.journal.swapped.len().to_string()),
+            ),
+            Err(_) => None,
+        };
+        self.journal.swapped.push(Swapped {
+            target: target.to_path_buf(),
+            backup: backup.clone(),
+        });
+        self.write_journal()?;
+
+        if let Some(backup) = backup {
+            try_move!(target, &backup);
+        }
+        if let Some(parent) = target.parent() {
+            match std::fs::create_dir_all(parent) {
+                Ok(_) => (),
+                Err(err) => return Err(ConfigCliError::FileCreationError(err).at(parent)),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_journal(&self) -> ConfigResult<()> {
+        let path = self.dir.join(JOURNAL);
+        let content = match toml::to_string(&self.journal) {
+            Ok(content) => content,
+            Err(err) => return Err(ConfigCliError::SerializeError(err).at(&path)),
+        };
+        match std::fs::write(&path, content) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(ConfigCliError::FsWriteError(err).at(&path)),
+        }
+    }
+
+    /// Removes the staging area and the backups
+    pub fn commit(self) -> ConfigResult<()> {
+        match remove_path(&self.dir) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(ConfigCliError::DeleteError(err).at(&self.dir)),
+        }
+    }
+
+    /// Puts every original back in reverse order, then returns the error that caused the rollback
+    pub fn rollback(self, cause: ConfigCliError) -> ConfigCliError {
+        match self.restore() {
+            Ok(_) => cause,
+            Err(err) => ConfigCliError::RollbackFailed(Box::new(cause), Box::new(err)),
+        }
+    }
+
+    fn restore(self) -> ConfigResult<()> {
+        for Swapped { target, backup } in self.journal.swapped.iter().rev() {
+            // Interrupted before the original was moved away, so it is still in place
+            if backup
+                .as_ref()
+                .is_some_and(|x| std::fs::symlink_metadata(x).is_err())
+            {
+                continue;
+            }
+            if std::fs::symlink_metadata(target).is_ok() {
+                match remove_path(target) {
+                    Ok(_) => (),
+                    Err(err) => return Err(ConfigCliError::DeleteError(err).at(target)),
+                }
+            }
+            if let Some(backup) = backup {
+                try_move!(backup, target);
+            }
+        }
+        self.commit()
+    }
+}
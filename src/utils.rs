@@ -1,14 +1,109 @@
 use crate::error::ConfigCliError;
 use serde::{Deserialize, Serialize};
-use std::ops::Deref;
-use std::path::Path;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 pub type ConfigResult<T> = Result<T, ConfigCliError>;
 
+// State every store on the machine shares, older versions kept it in the store
+const MACHINE_STATE: [&str; 1] = ["identity.txt"];
+// State kept for each store, older versions kept it in the store and then shared it between stores
+const STORE_STATE: [&str; 3] = ["current_theme.toml", ".backups", ".transaction"];
+
+static STORE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Uses the directory as the store for the rest of the run, taking precedence over the environment
+pub fn set_store_dir(path: PathBuf) -> ConfigResult<()> {
+    let _ = STORE_DIR.set(absolute_path(path)?);
+    Ok(())
+}
+
+/// The path made absolute against the working directory, without resolving symlinks
+pub fn absolute_path(path: impl AsRef<Path>) -> ConfigResult<PathBuf> {
+    match std::path::absolute(&path) {
+        Ok(path) => Ok(path),
+        Err(err) => Err(ConfigCliError::FsReadError(err).at(path)),
+    }
+}
+
+fn home_dir() -> ConfigResult<PathBuf> {
+    env_dir("HOME").ok_or(ConfigCliError::UnableToFindHomeDir)
+}
+
+// Empty and relative values are ignored, as the XDG base directory spec asks for
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute())
+}
+
+/// The store holding the themes, with a trailing slash
+///
+/// Resolved from `--store`, then `$CONFIG_MANAGER_HOME`, then `$XDG_DATA_HOME/configmanager`,
+/// falling back to `~/.local/share/configmanager`.
 pub fn get_base_dir() -> ConfigResult<String> {
-    #[allow(deprecated)]
-    let home_dir = std::env::home_dir().ok_or(ConfigCliError::UnableToFindHomeDir)?;
-    Ok(home_dir.deref().as_os_str().to_str().unwrap().to_owned() + "/.local/share/configmanager/")
+    let store = match STORE_DIR.get() {
+        Some(store) => store.clone(),
+        None => match std::env::var_os("CONFIG_MANAGER_HOME").filter(|x| !x.is_empty()) {
+            Some(store) => absolute_path(store)?,
+            None => match env_dir("XDG_DATA_HOME") {
+                Some(data_home) => data_home.join("configmanager"),
+                None => home_dir()?.join(".local/share/configmanager"),
+            },
+        },
+    };
+    Ok(store.join("").to_string_lossy().to_string())
+}
+
+/// Where the state of this machine is kept apart from the themes, like the age identity
+///
+/// Resolved from `$XDG_STATE_HOME/configmanager`, falling back to `~/.local/state/configmanager`.
+pub fn get_machine_state_dir() -> ConfigResult<PathBuf> {
+    match env_dir("XDG_STATE_HOME") {
+        Some(state_home) => Ok(state_home.join("configmanager")),
+        None => Ok(home_dir()?.join(".local/state/configmanager")),
+    }
+}
+
+/// Where the state of the store on this machine is kept, like the current theme and backups
+///
+/// Every store gets a directory of its own in `stores` of the machine state, named after a hash
+/// of its canonical path, so stores picked with `--store` don't share a current theme.
+pub fn get_state_dir() -> ConfigResult<PathBuf> {
+    let store: PathBuf = Path::new(&get_base_dir()?).components().collect();
+    let store = std::fs::canonicalize(&store).unwrap_or(store);
+    let hash = Sha256::digest(store.as_os_str().as_encoded_bytes());
+    let name: String = hash[..8].iter().map(|x| format!("{:02x}", x)).collect();
+    Ok(get_machine_state_dir()?.join("stores").join(name))
+}
+
+/// Moves state that older versions kept in the store or shared between stores to where it is kept
+///
+/// State shared by every store goes to the first store used after the upgrade.
+pub fn migrate_legacy_state() -> ConfigResult<()> {
+    let store = PathBuf::from(get_base_dir()?);
+    let machine_state = get_machine_state_dir()?;
+    let state = get_state_dir()?;
+
+    let moves = MACHINE_STATE
+        .iter()
+        .map(|x| (store.join(x), machine_state.join(x)))
+        .chain(STORE_STATE.iter().flat_map(|x| {
+            [
+                (store.join(x), state.join(x)),
+                (machine_state.join(x), state.join(x)),
+            ]
+        }));
+    for (old, new) in moves {
+        if std::fs::symlink_metadata(&old).is_err() || std::fs::symlink_metadata(&new).is_ok() {
+            continue;
+        }
+        if let Err(err) = move_path(&old, &new) {
+            return Err(ConfigCliError::FsWriteError(err).at(&old));
+        }
+    }
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize)]
@@ -18,12 +113,13 @@ pub struct CurrentTheme {
 
 #[macro_export]
 macro_rules! try_read_file {
-    ($path: expr) => {
-        match std::fs::read($path) {
+    ($path: expr) => {{
+        let path = $path;
+        match std::fs::read(&path) {
             Ok(file) => file,
-            Err(err) => return Err(ConfigCliError::FsReadError(err)),
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(&path)),
         }
-    };
+    }};
 }
 
 #[macro_export]
@@ -38,44 +134,49 @@ macro_rules! try_parse_toml {
 
 macro_rules! try_read_and_parse {
     ($path: expr, $type: ty) => {{
-        use crate::{try_parse_toml, try_read_file};
-
-        let file_contents = try_read_file!($path);
+        let path = $path;
+        let file_contents = $crate::try_read_file!(&path);
         let string = match std::str::from_utf8(&file_contents) {
             Ok(string) => string,
-            Err(err) => return Err(ConfigCliError::StringConversionError(err)),
+            Err(err) => return Err(ConfigCliError::StringConversionError(err).at(&path)),
         };
-        try_parse_toml!(string, $type)
+        match toml::from_str::<$type>(string) {
+            Ok(toml) => toml,
+            Err(err) => return Err(ConfigCliError::DeserializeError(err).at(&path)),
+        }
     }};
 }
 
 macro_rules! try_create_file {
-    ($path: expr) => {
-        match std::fs::File::create(&$path) {
+    ($path: expr) => {{
+        let path = $path;
+        match std::fs::File::create(&path) {
             Ok(_) => (),
-            Err(err) => return Err(ConfigCliError::FileCreationError(err)),
+            Err(err) => return Err(ConfigCliError::FileCreationError(err).at(&path)),
         }
-    };
+    }};
 }
 
 #[macro_export]
 macro_rules! try_symlink {
-    ($location:expr, $link:expr) => {
-        match std::os::unix::fs::symlink($location, $link) {
+    ($location:expr, $link:expr) => {{
+        let link = $link;
+        match std::os::unix::fs::symlink($location, &link) {
             Ok(_) => (),
-            Err(err) => return Err(ConfigCliError::SymlinkError(err)),
+            Err(err) => return Err(ConfigCliError::SymlinkError(err).at(&link)),
         }
-    };
+    }};
 }
 
 #[macro_export]
 macro_rules! try_rename {
-    ($src:expr, $dst:expr) => {
-        match std::fs::rename($src, $dst) {
+    ($src:expr, $dst:expr) => {{
+        let src = $src;
+        match std::fs::rename(&src, $dst) {
             Ok(_) => (),
-            Err(err) => return Err(ConfigCliError::RenameError(err)),
+            Err(err) => return Err(ConfigCliError::RenameError(err).at(&src)),
         }
-    };
+    }};
 }
 
 #[macro_export]
@@ -84,64 +185,91 @@ macro_rules! try_write_file {
         use std::fs::File;
         use std::io::Write;
 
-        let mut file_handle = match File::options().write(true).open($location) {
+        let location = $location;
+        let mut file_handle = match File::options().write(true).truncate(true).open(&location) {
             Ok(file) => file,
-            Err(err) => return Err(ConfigCliError::FsReadError(err)),
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(&location)),
         };
 
         let content_string = match toml::to_string($content) {
             Ok(s) => s,
-            Err(err) => return Err(ConfigCliError::SerializeError(err)),
+            Err(err) => return Err(ConfigCliError::SerializeError(err).at(&location)),
         };
 
         match file_handle.write_all(content_string.as_bytes()) {
             Ok(_) => (),
-            Err(err) => return Err(ConfigCliError::FsWriteError(err)),
+            Err(err) => return Err(ConfigCliError::FsWriteError(err).at(&location)),
         }
 
         match file_handle.flush() {
             Ok(_) => (),
-            Err(err) => return Err(ConfigCliError::FsWriteError(err)),
+            Err(err) => return Err(ConfigCliError::FsWriteError(err).at(&location)),
         }
     }};
 }
 
 #[macro_export]
 macro_rules! try_delete {
-    ($path: expr) => {
-        match std::fs::remove_file($path) {
+    ($path: expr) => {{
+        let path = $path;
+        match std::fs::remove_file(&path) {
             Ok(_) => (),
-            Err(err) => return Err(ConfigCliError::DeleteError(err)),
+            Err(err) => return Err(ConfigCliError::DeleteError(err).at(&path)),
         }
-    };
+    }};
 }
 
 #[macro_export]
 macro_rules! try_copy_recursive {
-    ($src: expr, $dst: expr) => {
-        match copy_dir_all($src, $dst) {
+    ($src: expr, $dst: expr) => {{
+        let src = $src;
+        match copy_dir_all(&src, $dst) {
             Ok(_) => (),
-            Err(err) => return Err(ConfigCliError::CopyError(err)),
+            Err(err) => return Err(ConfigCliError::CopyError(err).at(&src)),
         }
-    };
+    }};
 }
 
 #[macro_export]
 macro_rules! try_delete_recursive {
-    ($loc: expr) => {
-        match std::fs::remove_dir_all($loc) {
+    ($loc: expr) => {{
+        let loc = $loc;
+        match std::fs::remove_dir_all(&loc) {
             Ok(_) => (),
-            Err(err) => return Err(ConfigCliError::DeleteError(err)),
+            Err(err) => return Err(ConfigCliError::DeleteError(err).at(&loc)),
         }
-    };
+    }};
+}
+
+#[macro_export]
+macro_rules! try_remove_path {
+    ($loc: expr) => {{
+        let loc = $loc;
+        match remove_path(&loc) {
+            Ok(_) => (),
+            Err(err) => return Err(ConfigCliError::DeleteError(err).at(&loc)),
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! try_move {
+    ($src: expr, $dst: expr) => {{
+        let src = $src;
+        match move_path(&src, $dst) {
+            Ok(_) => (),
+            Err(err) => return Err(ConfigCliError::RenameError(err).at(&src)),
+        }
+    }};
 }
 
 #[macro_export]
 macro_rules! try_read_dir {
     ($path: expr) => {{
-        let read_dir: ConfigResult<std::fs::ReadDir> = match std::fs::read_dir($path) {
+        let path = $path;
+        let read_dir: ConfigResult<std::fs::ReadDir> = match std::fs::read_dir(&path) {
             Ok(dir) => Ok(dir),
-            Err(err) => return Err(ConfigCliError::FsReadError(err)),
+            Err(err) => return Err(ConfigCliError::FsReadError(err).at(&path)),
         };
         read_dir
     }};
@@ -149,21 +277,10 @@ macro_rules! try_read_dir {
 
 #[macro_export]
 macro_rules! try_git {
-    ($command: expr, $current_dir: expr) => {
-        match Command::new("sh")
-            .arg("-c")
-            .current_dir($current_dir)
-            .arg(format!(r#"git {}"#, $command))
-            .output()
-        {
-            Ok(out) => {
-                if out.stderr.len() > 0 {
-                    return Err(ConfigCliError::GitCommandError(
-                        std::str::from_utf8(&out.stderr).unwrap().to_owned(),
-                    ));
-                }
-            }
-            Err(err) => return Err(ConfigCliError::ShellInitError(err)),
+    ($result: expr) => {
+        match $result {
+            Ok(x) => x,
+            Err(err) => return Err(ConfigCliError::GitError(err)),
         }
     };
 }
@@ -173,25 +290,197 @@ pub(crate) use try_create_file;
 pub(crate) use try_read_and_parse;
 
 pub fn get_current_theme() -> ConfigResult<String> {
-    let base_dir = get_base_dir()? + "current_theme.toml";
+    let current_theme_path = get_state_dir()?.join("current_theme.toml");
 
-    if !Path::new(&base_dir).exists() {
-        try_create_file!(base_dir);
+    if !current_theme_path.exists() {
+        try_create_file!(current_theme_path);
         return Err(ConfigCliError::NoThemeSelecected);
     }
-    Ok(try_read_and_parse!(base_dir, CurrentTheme).current_theme)
+    // The file is created empty before any theme was used
+    let is_empty = std::fs::metadata(&current_theme_path).is_ok_and(|x| x.len() == 0);
+    if is_empty {
+        return Err(ConfigCliError::NoThemeSelecected);
+    }
+    Ok(try_read_and_parse!(current_theme_path, CurrentTheme).current_theme)
 }
 
+/// Copies a file or a directory with everything below it, keeping the mode bits of each
 pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result<()> {
+    // Single file configs are copied directly
+    if !src.as_ref().is_dir() {
+        if let Some(parent) = dst.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(src, dst)?;
+        return Ok(());
+    }
     std::fs::create_dir_all(&dst)?;
-    for entry in std::fs::read_dir(src)? {
+    for entry in std::fs::read_dir(&src)? {
         let entry = entry?;
         let ty = entry.file_type()?;
         if ty.is_dir() {
             copy_dir_all(entry.path(), dst.as_ref().join(entry.file_name()))?;
-        } else {
+        } else if ty.is_file() || ty.is_symlink() {
             std::fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))?;
         }
+        // Sockets, pipes and devices only mean something to whoever created them
+    }
+    // Set last, so a read-only directory still gets its content
+    std::fs::set_permissions(&dst, std::fs::metadata(&src)?.permissions())
+}
+
+/// Looks up an executable in the directories listed in `$PATH`
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Removes whatever is at the path without following symlinks
+pub fn remove_path(path: impl AsRef<Path>) -> std::io::Result<()> {
+    let meta = std::fs::symlink_metadata(&path)?;
+    if meta.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// Whether the value matches the glob, `*` and `?` work like in the shell
+pub fn glob_matches(pattern: &str, value: &str) -> ConfigResult<bool> {
+    match globset::Glob::new(pattern) {
+        Ok(glob) => Ok(glob.compile_matcher().is_match(value)),
+        Err(err) => Err(ConfigCliError::InvalidPattern(err.to_string())),
+    }
+}
+
+/// The absolute path moved below the root, like it is seen from outside a chroot
+pub fn reroot(root: &Path, path: &Path) -> PathBuf {
+    root.join(path.strip_prefix("/").unwrap_or(path))
+}
+
+/// A relative link from the location of the link to the destination, both being absolute
+pub fn relative_link(link: &Path, destination: &Path) -> PathBuf {
+    let from: Vec<_> = link.parent().unwrap_or(link).components().collect();
+    let to: Vec<_> = destination.components().collect();
+    let common = from.iter().zip(&to).take_while(|x| x.0 == x.1).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &to[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+/// Where the value of a link points to, relative values are taken from the directory of the link
+pub fn resolve_link(link: &Path, value: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in link.parent().unwrap_or(link).join(value).components() {
+        match component {
+            std::path::Component::ParentDir => {
+                resolved.pop();
+            }
+            std::path::Component::CurDir => (),
+            component => resolved.push(component),
+        }
+    }
+    resolved
+}
+
+/// Copies the path, keeping a symlink as a symlink instead of copying what it points to
+pub fn copy_path(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result<()> {
+    let meta = std::fs::symlink_metadata(&src)?;
+    if !meta.file_type().is_symlink() {
+        return copy_dir_all(src, dst);
+    }
+    if let Some(parent) = dst.as_ref().parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::os::unix::fs::symlink(std::fs::read_link(&src)?, dst)
+}
+
+/// Renames the path, falling back to copy and delete when crossing filesystems
+pub fn move_path(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result<()> {
+    if std::fs::rename(&src, &dst).is_ok() {
+        return Ok(());
+    }
+    let meta = std::fs::symlink_metadata(&src)?;
+    if meta.file_type().is_symlink() {
+        std::os::unix::fs::symlink(std::fs::read_link(&src)?, &dst)?;
+    } else {
+        copy_dir_all(&src, &dst)?;
+    }
+    remove_path(src)
+}
+
+/// SHA-256 over the content of a file, or over every entry of a directory in a stable order
+pub fn hash_path(path: impl AsRef<Path>) -> std::io::Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    hash_into(path.as_ref(), Path::new(""), &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+fn hash_into(path: &Path, relative: &Path, hasher: &mut Sha256) -> std::io::Result<()> {
+    let meta = std::fs::symlink_metadata(path)?;
+    hasher.update(relative.as_os_str().as_encoded_bytes());
+
+    if meta.file_type().is_symlink() {
+        hasher.update(b"l");
+        hasher.update(std::fs::read_link(path)?.as_os_str().as_encoded_bytes());
+    } else if meta.is_dir() {
+        hasher.update(b"d");
+        let mut entries = std::fs::read_dir(path)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|x| x.file_name());
+        for entry in entries {
+            hash_into(&entry.path(), &relative.join(entry.file_name()), hasher)?;
+        }
+    } else {
+        hasher.update(b"f");
+        hasher.update(std::fs::read(path)?);
     }
     Ok(())
 }
+
+/// Seconds since the unix epoch
+pub fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
+
+/// Formats unix seconds as a sortable UTC timestamp like `20240131T235959`
+pub fn timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let time = secs % 86400;
+
+    // Converts days since the epoch into a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
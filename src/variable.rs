@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::device::device_lineage;
+use crate::error::ConfigCliError;
+use crate::facts::builtin_facts;
+use crate::secret::decrypt_if_recipient;
+use crate::theme::{load_manifest, theme_chain};
+use crate::utils::ConfigResult;
+
+pub mod list;
+pub mod remove;
+pub mod set;
+
+pub use list::*;
+pub use remove::*;
+pub use set::*;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct VariableFile {
+    #[serde(default)]
+    pub globals: BTreeMap<String, String>,
+    #[serde(default)]
+    pub device_bounds: Vec<(String, BTreeMap<String, String>)>,
+    /// Like `globals`, but the values are age encrypted
+    #[serde(default)]
+    pub secrets: BTreeMap<String, String>,
+    #[serde(default)]
+    pub device_secrets: Vec<(String, BTreeMap<String, String>)>,
+}
+
+// A value of `None` is a secret this machine can't decrypt, it hides the variable
+type Values = BTreeMap<String, Option<String>>;
+
+impl VariableFile {
+    /// Theme wide variables with the secrets decrypted
+    fn global_values(&self) -> ConfigResult<Values> {
+        let mut values: Values = self
+            .globals
+            .iter()
+            .map(|(name, value)| (name.clone(), Some(value.clone())))
+            .collect();
+        values.extend(decrypt_values(&self.secrets)?);
+        Ok(values)
+    }
+
+    fn device_values(&self, device: &str) -> ConfigResult<Values> {
+        let mut values = Values::new();
+        for (_, variables) in self.device_bounds.iter().filter(|x| x.0 == device) {
+            values.extend(variables.iter().map(|(n, v)| (n.clone(), Some(v.clone()))));
+        }
+        for (_, secrets) in self.device_secrets.iter().filter(|x| x.0 == device) {
+            values.extend(decrypt_values(secrets)?);
+        }
+        Ok(values)
+    }
+
+    pub fn secret_names(&self) -> impl Iterator<Item = &String> {
+        self.secrets
+            .keys()
+            .chain(self.device_secrets.iter().flat_map(|x| x.1.keys()))
+    }
+}
+
+fn decrypt_values(secrets: &BTreeMap<String, String>) -> ConfigResult<Values> {
+    let mut values = Values::new();
+    for (name, ciphertext) in secrets {
+        let value = match decrypt_if_recipient(ciphertext.as_bytes())? {
+            Some(plaintext) => match String::from_utf8(plaintext) {
+                Ok(value) => Some(value),
+                Err(err) => return Err(ConfigCliError::StringConversionError(err.utf8_error())),
+            },
+            None => None,
+        };
+        values.insert(name.clone(), value);
+    }
+    Ok(values)
+}
+
+pub fn read_variable_file(theme: &str) -> ConfigResult<VariableFile> {
+    Ok(load_manifest(theme)?.variables)
+}
+
+/// Resolves the variables a template sees, later sources overriding earlier ones:
+///
+/// 1. built-in facts plus `theme` and `device`
+/// 2. theme variables, from the root of the inheritance chain down to the theme
+/// 3. variables bound to each name of the device lineage in turn, see [`device_lineage`], every
+///    name in the same order as the theme variables
+///
+/// Secrets this machine is not a recipient of are left out, so only templates using them fail.
+pub fn resolve_variables(
+    theme: &str,
+    device: Option<&str>,
+) -> ConfigResult<BTreeMap<String, String>> {
+    let variable_files = theme_chain(theme)?
+        .iter()
+        .map(|x| read_variable_file(x))
+        .collect::<ConfigResult<Vec<VariableFile>>>()?;
+
+    let mut variables = builtin_facts();
+    variables.insert("theme".to_owned(), theme.to_owned());
+    if let Some(device) = device {
+        variables.insert("device".to_owned(), device.to_owned());
+    }
+
+    let mut layers = vec![];
+    for variable_file in &variable_files {
+        layers.push(variable_file.global_values()?);
+    }
+    if let Some(device) = device {
+        for name in device_lineage(theme, device)? {
+            for variable_file in &variable_files {
+                layers.push(variable_file.device_values(&name)?);
+            }
+        }
+    }
+    for (name, value) in layers.into_iter().flatten() {
+        match value {
+            Some(value) => variables.insert(name, value),
+            None => variables.remove(&name),
+        };
+    }
+    Ok(variables)
+}
@@ -0,0 +1,24 @@
+use std::collections::BTreeMap;
+
+use super::{read_variable_file, resolve_variables};
+use crate::theme::theme_chain;
+use crate::utils::ConfigResult;
+
+/// Lists the variables a template would see on the device, including the built-in facts
+///
+/// Values of secret variables are masked.
+pub fn list_variables(
+    theme: String,
+    device: Option<String>,
+) -> ConfigResult<BTreeMap<String, String>> {
+    let mut variables = resolve_variables(&theme, device.as_deref())?;
+
+    for layer in theme_chain(&theme)? {
+        for name in read_variable_file(&layer)?.secret_names() {
+            if let Some(value) = variables.get_mut(name) {
+                *value = "********".to_owned();
+            }
+        }
+    }
+    Ok(variables)
+}
@@ -0,0 +1,35 @@
+use crate::error::ConfigCliError;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::ConfigResult;
+
+pub fn remove_variable(theme: String, name: String, device: Option<String>) -> ConfigResult<()> {
+    let mut manifest = load_manifest(&theme)?;
+    let variable_file = &mut manifest.variables;
+
+    let removed = match device {
+        Some(device) => {
+            variable_file
+                .device_bounds
+                .iter_mut()
+                .chain(variable_file.device_secrets.iter_mut())
+                .filter(|x| x.0 == device)
+                .map(|x| x.1.remove(&name).is_some())
+                .filter(|removed| *removed)
+                .count()
+                > 0
+        }
+        None => {
+            let removed = variable_file.globals.remove(&name).is_some();
+            variable_file.secrets.remove(&name).is_some() || removed
+        }
+    };
+
+    if !removed {
+        return Err(ConfigCliError::InvalidVariableName(name));
+    }
+
+    variable_file.device_bounds.retain(|x| !x.1.is_empty());
+    variable_file.device_secrets.retain(|x| !x.1.is_empty());
+    save_manifest(&theme, &manifest)?;
+    Ok(())
+}
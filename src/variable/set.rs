@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use crate::error::ConfigCliError;
+use crate::secret::encrypt;
+use crate::theme::{load_manifest, save_manifest};
+use crate::utils::ConfigResult;
+
+pub fn set_variable(
+    theme: String,
+    name: String,
+    value: String,
+    device: Option<String>,
+    secret: bool,
+) -> ConfigResult<()> {
+    let mut manifest = load_manifest(&theme)?;
+    let variable_file = &mut manifest.variables;
+
+    let value = match secret {
+        true => match String::from_utf8(encrypt(&theme, value.as_bytes())?) {
+            Ok(ciphertext) => ciphertext,
+            Err(err) => return Err(ConfigCliError::StringConversionError(err.utf8_error())),
+        },
+        false => value,
+    };
+
+    // A variable is either plain or secret, never both
+    let (table, other) = match (device, secret) {
+        (Some(device), true) => (
+            device_table(&mut variable_file.device_secrets, device.clone()),
+            device_table(&mut variable_file.device_bounds, device),
+        ),
+        (Some(device), false) => (
+            device_table(&mut variable_file.device_bounds, device.clone()),
+            device_table(&mut variable_file.device_secrets, device),
+        ),
+        (None, true) => (&mut variable_file.secrets, &mut variable_file.globals),
+        (None, false) => (&mut variable_file.globals, &mut variable_file.secrets),
+    };
+    other.remove(&name);
+    table.insert(name, value);
+
+    variable_file.device_bounds.retain(|x| !x.1.is_empty());
+    variable_file.device_secrets.retain(|x| !x.1.is_empty());
+    save_manifest(&theme, &manifest)?;
+    Ok(())
+}
+
+fn device_table(
+    tables: &mut Vec<(String, BTreeMap<String, String>)>,
+    device: String,
+) -> &mut BTreeMap<String, String> {
+    let index = match tables.iter().position(|x| x.0 == device) {
+        Some(index) => index,
+        None => {
+            tables.push((device, BTreeMap::new()));
+            tables.len() - 1
+        }
+    };
+    &mut tables[index].1
+}
@@ -0,0 +1,47 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+// A fresh home for the run, so the store and state of the test don't touch the user's
+fn temp_home(name: &str) -> PathBuf {
+    let home = std::env::temp_dir().join(format!("config_cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&home);
+    std::fs::create_dir_all(&home).unwrap();
+    home
+}
+
+fn run(home: &Path, args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_config_cli"))
+        .args(args)
+        .env("HOME", home)
+        .env_remove("XDG_DATA_HOME")
+        .env_remove("XDG_STATE_HOME")
+        .env_remove("CONFIG_MANAGER_HOME")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+#[test]
+fn lists_the_dependencies_of_a_config() {
+    let home = temp_home("dependency-list");
+    let file = home.join("kitty.conf");
+    std::fs::write(&file, "font_size 12\n").unwrap();
+
+    run(&home, &["theme", "create", "dark"]);
+    run(&home, &["theme", "use", "dark"]);
+    run(&home, &["config", "add", "kitty", file.to_str().unwrap()]);
+    run(&home, &["dependency", "add", "kitty-pkg", "kitty"]);
+    run(&home, &["dependency", "add", "unrelated-pkg"]);
+
+    let output = run(&home, &["dependency", "list", "-o", "json", "kitty"]);
+    let listed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(listed["data"], serde_json::json!(["kitty-pkg"]));
+
+    std::fs::remove_dir_all(&home).unwrap();
+}